@@ -49,6 +49,7 @@ const MONITOR_SIZE_WINDOW_RATIO: f64 = 0.8;
 
 fn main() {
     let args = Args::arg().with_help_default().parse_env_default_or_exit();
+    let wfc_sample = args.common.wfc_sample();
     let grid_size = gws_prototty::APP_SIZE;
     let font_size = match args.font_size {
         FontSize::Specified(font_size) => font_size,
@@ -82,6 +83,7 @@ fn main() {
         storage,
         args.common.first_rng_seed(),
         args.common.debug_terrain_string(),
+        wfc_sample,
     );
     let mut input_buffer = Vec::with_capacity(64);
     let mut app_view = AppView::new();
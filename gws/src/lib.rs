@@ -17,6 +17,9 @@ mod vision;
 mod world;
 
 pub use crate::pathfinding::*;
+pub use crate::terrain::{
+    Base, Palette, PaletteEntry, PaletteError, SampleError, SampleProblem, WfcSample,
+};
 use crate::vision::*;
 pub use crate::world::*;
 use coord_2d::*;
@@ -25,15 +28,24 @@ use line_2d::*;
 use rand::seq::SliceRandom;
 use rand::Rng;
 use rgb24::*;
+use std::mem;
 use std::time::Duration;
 
 const NPC_VISION_RANGE: usize = 16;
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Input {
     Move(CardinalDirection),
-    PlayCard { slot: usize, param: CardParam },
+    PlayCard {
+        slot: usize,
+        param: CardParam,
+    },
     Interact(InteractiveParam),
+    CloseDoor(CardinalDirection),
+    UseItem(Item),
+    /// Discard the cards in these hand slots and draw replacements.
+    Mulligan(Vec<usize>),
+    Wait,
 }
 
 pub mod input {
@@ -42,15 +54,34 @@ pub mod input {
     pub const DOWN: Input = Input::Move(CardinalDirection::South);
     pub const LEFT: Input = Input::Move(CardinalDirection::West);
     pub const RIGHT: Input = Input::Move(CardinalDirection::East);
+    pub const WAIT: Input = Input::Wait;
     pub fn play_card(slot: usize, param: CardParam) -> Input {
         Input::PlayCard { slot, param }
     }
     pub fn interact(param: InteractiveParam) -> Input {
         Input::Interact(param)
     }
+    pub fn close_door(direction: CardinalDirection) -> Input {
+        Input::CloseDoor(direction)
+    }
+    pub fn use_item(item: Item) -> Input {
+        Input::UseItem(item)
+    }
+    pub fn mulligan(slots: &[usize]) -> Input {
+        Input::Mulligan(slots.to_vec())
+    }
 }
 
 const INITIAL_DRAW_COUNTDOWN: u32 = 40;
+const WATER_MOVE_COST: u32 = 2;
+const REGENERATION_PERIOD: u32 = 4;
+const SLAM_DAMAGE: u32 = 2;
+const SUMMON_PERIOD: u32 = 6;
+const FREEZE_TURNS: u32 = 8;
+const FREEZE_PLUS_TURNS: u32 = 16;
+const BLINK_PLUS_RANGE: u32 = 12;
+/// Power spent to discard part of a hand and draw replacements.
+pub const MULLIGAN_COST: u32 = 10;
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct DrawCountdown {
@@ -75,6 +106,29 @@ pub struct Gws {
     burnt: Vec<Card>,
     draw_countdown: DrawCountdown,
     level: u32,
+    objective: Option<Objective>,
+    turns_taken: u32,
+    healing_used: bool,
+    boss_id: Option<EntityId>,
+    settings: RunSettings,
+    relics: Vec<Relic>,
+    card_played_this_hand: bool,
+    mulligan_used_this_hand: bool,
+    items: Vec<Item>,
+    gold: u32,
+    route: Vec<Branch>,
+    /// Levels which have been left, so they can be returned to.
+    stored_levels: Vec<StoredLevel>,
+    /// The number of times the deck has been reshuffled on this level.
+    reshuffles: u32,
+    /// Whether this level's rewards were given when it was first left by the way down.
+    rewards_paid: bool,
+    start_coord: Coord,
+    /// The number of times an item was left on the floor because the inventory was full.
+    items_left_behind: u32,
+    /// Where the player is standing on an item they couldn't carry, so it's only counted
+    /// once for each time they step onto it.
+    item_left_behind_at: Option<Coord>,
 }
 
 pub struct ToRender<'a> {
@@ -93,6 +147,255 @@ enum TerrainChoice {
 const TERRAIN_CHOICE: TerrainChoice = TerrainChoice::WfcIceCave(Size::new_u16(60, 40));
 //const TERRAIN_CHOICE: TerrainChoice = TerrainChoice::StringDemo;
 
+/// Number of levels in a standard game. The last one ends with the Guardian.
+pub const NUM_LEVELS: u32 = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    Standard,
+    /// Levels keep going past the final level, growing harder with depth.
+    Endless,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+pub const ALL_DIFFICULTIES: &[Difficulty] =
+    &[Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+/// What happens when a hand is drawn from an empty deck.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReshuffleRule {
+    /// Hands stay empty until the next level.
+    Never,
+    /// The waste pile is shuffled back into the deck.
+    Waste,
+    /// The spent and waste piles are shuffled back into the deck.
+    SpentAndWaste,
+    /// As `SpentAndWaste`, but each reshuffle on a level deals 1 more damage than the last.
+    Fatigue,
+}
+
+pub const ALL_RESHUFFLE_RULES: &[ReshuffleRule] = &[
+    ReshuffleRule::Never,
+    ReshuffleRule::Waste,
+    ReshuffleRule::SpentAndWaste,
+    ReshuffleRule::Fatigue,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mutator {
+    /// Start with a Clog in the deck.
+    Curse,
+    /// Enemies have an extra hit point.
+    ToughEnemies,
+    NoHealthPickups,
+    /// Start with twice the maximum power.
+    DoublePower,
+    /// Nothing but the player gives off light.
+    Permadark,
+}
+
+pub const ALL_MUTATORS: &[Mutator] = &[
+    Mutator::Curse,
+    Mutator::ToughEnemies,
+    Mutator::NoHealthPickups,
+    Mutator::DoublePower,
+    Mutator::Permadark,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayerClass {
+    Wanderer,
+    Warrior,
+    Trapper,
+    Mage,
+}
+
+pub const ALL_PLAYER_CLASSES: &[PlayerClass] = &[
+    PlayerClass::Wanderer,
+    PlayerClass::Warrior,
+    PlayerClass::Trapper,
+    PlayerClass::Mage,
+];
+
+impl PlayerClass {
+    pub fn deck(self) -> Vec<Card> {
+        use Card::*;
+        match self {
+            PlayerClass::Wanderer => vec![
+                Bump, Bump, Heal, Heal, Block, Block, Freeze, Freeze, Spike, Spike,
+                Spike, Empower, Empower, Blink, Blink, Deposit, Deposit, Caltrop, Spark,
+                Spark, Bash, Bash,
+            ],
+            PlayerClass::Warrior => vec![
+                Bump, Bump, Bump, Bump, Bump, Bash, Bash, Bash, Bash, Smash, Heal, Heal,
+                Block, Block, Armour, Empower, Empower, Blink, Blink, Deposit,
+            ],
+            PlayerClass::Trapper => vec![
+                Spike, Spike, Spike, Spike, Spike, Block, Block, Block, Block, Caltrop,
+                Caltrop, Caltrop, Deposit, Deposit, Freeze, Freeze, Bump, Bump, Heal,
+                Heal, Blink, Blink,
+            ],
+            PlayerClass::Mage => vec![
+                Spark, Spark, Spark, Spark, Spark, Blast, Blast, Freeze, Freeze, Freeze,
+                Freeze, Blink, Blink, Blink, Heal, Heal, Empower, Empower, Bump, Block,
+            ],
+        }
+    }
+    pub fn hit_points(self) -> u32 {
+        match self {
+            PlayerClass::Wanderer => 3,
+            PlayerClass::Warrior => 5,
+            PlayerClass::Trapper => 3,
+            PlayerClass::Mage => 2,
+        }
+    }
+    pub fn hand_size(self) -> usize {
+        match self {
+            PlayerClass::Wanderer => 5,
+            PlayerClass::Warrior => 4,
+            PlayerClass::Trapper => 6,
+            PlayerClass::Mage => 5,
+        }
+    }
+    pub fn max_power(self) -> u32 {
+        match self {
+            PlayerClass::Wanderer => INITIAL_DRAW_COUNTDOWN,
+            PlayerClass::Warrior => INITIAL_DRAW_COUNTDOWN,
+            PlayerClass::Trapper => INITIAL_DRAW_COUNTDOWN - 10,
+            PlayerClass::Mage => INITIAL_DRAW_COUNTDOWN + 20,
+        }
+    }
+    pub fn light_range_squared(self) -> u32 {
+        match self {
+            PlayerClass::Wanderer => 60,
+            PlayerClass::Warrior => 40,
+            PlayerClass::Trapper => 60,
+            PlayerClass::Mage => 100,
+        }
+    }
+}
+
+/// Choices made when starting a new game which apply for the whole run.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunSettings {
+    pub player_class: PlayerClass,
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub mutators: Vec<Mutator>,
+    pub reshuffle: ReshuffleRule,
+}
+
+impl Default for RunSettings {
+    fn default() -> Self {
+        Self {
+            player_class: PlayerClass::Wanderer,
+            mode: GameMode::Standard,
+            difficulty: Difficulty::Normal,
+            mutators: Vec::new(),
+            reshuffle: ReshuffleRule::SpentAndWaste,
+        }
+    }
+}
+
+impl RunSettings {
+    pub fn has_mutator(&self, mutator: Mutator) -> bool {
+        self.mutators.contains(&mutator)
+    }
+    pub fn toggle_mutator(&mut self, mutator: Mutator) {
+        if let Some(index) = self.mutators.iter().position(|&m| m == mutator) {
+            self.mutators.remove(index);
+        } else {
+            self.mutators.push(mutator);
+        }
+    }
+    // mutators which change the entities placed by the level generator
+    fn mutate_instruction(&self, instruction: Instruction) -> Instruction {
+        match instruction {
+            Instruction::AddEntity(coord, mut packed_entity) => {
+                if packed_entity.npc && self.has_mutator(Mutator::ToughEnemies) {
+                    if let Some(hit_points) = packed_entity.hit_points.as_mut() {
+                        hit_points.max += 1;
+                        hit_points.current += 1;
+                    }
+                }
+                if self.has_mutator(Mutator::Permadark) {
+                    packed_entity.light = None;
+                }
+                Instruction::AddEntity(coord, packed_entity)
+            }
+            other => other,
+        }
+    }
+}
+
+const EASY_EXTRA_HIT_POINTS: u32 = 2;
+
+/// A level which has been left, kept as it was so that it can be returned to.
+#[derive(Clone, Serialize, Deserialize)]
+struct StoredLevel {
+    level: u32,
+    branch: Branch,
+    world: World,
+    visible_area: VisibileArea,
+    objective: Option<Objective>,
+    turns_taken: u32,
+    healing_used: bool,
+    boss_id: Option<EntityId>,
+    rewards_paid: bool,
+    /// Where the player first arrived on this level.
+    start_coord: Coord,
+}
+
+impl StoredLevel {
+    /// The free cell closest to the stairs the player arrives by.
+    fn arrival_coord(&self, arrival: Arrival) -> Coord {
+        let stairs_coord = self
+            .world
+            .entities()
+            .values()
+            .find(|entity| match arrival {
+                Arrival::Descending => {
+                    entity.foreground_tile() == Some(ForegroundTile::UpStairs)
+                }
+                Arrival::Ascending(branch) => {
+                    entity.foreground_tile() == Some(ForegroundTile::Stairs)
+                        && entity.branch() == Some(branch)
+                }
+            })
+            .map(Entity::coord)
+            .unwrap_or(self.start_coord);
+        self.world
+            .grid()
+            .coord_iter()
+            .filter(|&coord| {
+                self.world.is_free_for_summon(coord)
+                    && self
+                        .world
+                        .grid()
+                        .get_checked(coord)
+                        .entity_iter(self.world.entities())
+                        .next()
+                        .is_none()
+            })
+            .min_by_key(|coord| coord.manhattan_distance(stairs_coord))
+            .unwrap_or(self.start_coord)
+    }
+}
+
+/// Which way the player is going between levels.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Arrival {
+    Descending,
+    /// Climbing back up the stairs which lead to the given branch.
+    Ascending(Branch),
+}
+
 #[derive(Clone)]
 pub struct BetweenLevels {
     player: PackedEntity,
@@ -101,38 +404,46 @@ pub struct BetweenLevels {
     hand_size: usize,
     max_draw_countdown: u32,
     next_level: u32,
+    bonus_upgrades: u32,
+    reward_pending: bool,
+    settings: RunSettings,
+    relics: Vec<Relic>,
+    items: Vec<Item>,
+    gold: u32,
+    /// The branch taken on each level so far, ending with the next level's.
+    route: Vec<Branch>,
+    stored_levels: Vec<StoredLevel>,
+    arrival: Arrival,
 }
 
 impl BetweenLevels {
-    fn initial() -> Self {
-        let player = PackedEntity::player();
-        let deck = vec![
-            Card::Bump,
-            Card::Bump,
-            Card::Heal,
-            Card::Heal,
-            Card::Block,
-            Card::Block,
-            Card::Freeze,
-            Card::Freeze,
-            Card::Spike,
-            Card::Spike,
-            Card::Spike,
-            Card::Empower,
-            Card::Empower,
-            Card::Blink,
-            Card::Blink,
-            Card::Deposit,
-            Card::Deposit,
-            Card::Caltrop,
-            Card::Spark,
-            Card::Spark,
-            Card::Bash,
-            Card::Bash,
-        ];
+    pub fn new_game(settings: RunSettings) -> Self {
+        let player_class = settings.player_class;
+        let mut player = PackedEntity::player();
+        player.hit_points = Some(HitPoints::new(
+            player_class.hit_points(),
+            player_class.hit_points(),
+        ));
+        if let Some(light) = player.light.as_mut() {
+            light.range_squared = player_class.light_range_squared();
+        }
+        if settings.difficulty == Difficulty::Easy {
+            if let Some(hit_points) = player.hit_points.as_mut() {
+                hit_points.max += EASY_EXTRA_HIT_POINTS;
+                hit_points.current += EASY_EXTRA_HIT_POINTS;
+            }
+        }
+        let mut deck = settings.player_class.deck();
+        if settings.has_mutator(Mutator::Curse) {
+            deck.push(Card::Clog);
+        }
         let burnt = Vec::new();
-        let hand_size = 5;
-        let max_draw_countdown = INITIAL_DRAW_COUNTDOWN;
+        let hand_size = player_class.hand_size();
+        let max_draw_countdown = if settings.has_mutator(Mutator::DoublePower) {
+            player_class.max_power() * 2
+        } else {
+            player_class.max_power()
+        };
         Self {
             player,
             deck,
@@ -140,12 +451,217 @@ impl BetweenLevels {
             hand_size,
             max_draw_countdown,
             next_level: 0,
+            bonus_upgrades: 0,
+            reward_pending: false,
+            settings,
+            relics: Vec::new(),
+            items: Vec::new(),
+            gold: 0,
+            route: vec![Branch::Main],
+            stored_levels: Vec::new(),
+            arrival: Arrival::Descending,
+        }
+    }
+
+    /// Extra character upgrades earned by completing the previous level's objective and
+    /// killing elites.
+    pub fn bonus_upgrades(&self) -> u32 {
+        self.bonus_upgrades
+    }
+
+    pub fn choose_bonus_upgrades<R: Rng>(
+        &self,
+        amount: usize,
+        rng: &mut R,
+    ) -> impl Iterator<Item = &'static CharacterUpgrade> {
+        choose_character_upgrades(self.hand_size, amount, rng)
+    }
+
+    pub fn apply_bonus_upgrade(&mut self, character_upgrade: CharacterUpgrade) {
+        use CharacterUpgrade::*;
+        match character_upgrade {
+            Life => {
+                if let Some(hit_points) = self.player.hit_points.as_mut() {
+                    hit_points.max += LIFE_UPGRADE_HIT_POINTS;
+                }
+            }
+            Power => self.max_draw_countdown += POWER_UPGRADE_DRAW_COUNTDOWN,
+            Hand => self.hand_size += 1,
+            Vision => {
+                if let Some(light) = self.player.light.as_mut() {
+                    light.range_squared += VISION_UPGRADE_LIGHT_RANGE_SQUARED;
+                }
+            }
+        }
+        self.bonus_upgrades = self.bonus_upgrades.saturating_sub(1);
+    }
+
+    /// True until a reward has been chosen (or skipped) after leaving a level.
+    pub fn reward_pending(&self) -> bool {
+        self.reward_pending
+    }
+
+    pub fn deck(&self) -> &[Card] {
+        &self.deck
+    }
+
+    pub fn route(&self) -> &[Branch] {
+        &self.route
+    }
+
+    /// Removing a card is only allowed while the deck can still fill a hand.
+    pub fn can_remove_card(&self) -> bool {
+        self.deck.len() > self.hand_size
+    }
+
+    /// Distinct cards which can be found on the next level.
+    pub fn choose_reward_cards<R: Rng>(&self, amount: usize, rng: &mut R) -> Vec<Card> {
+        let mut card_dist = terrain::card_dist(self.next_level, &self.settings);
+        card_dist.sort();
+        card_dist.dedup();
+        card_dist.choose_multiple(rng, amount).cloned().collect()
+    }
+
+    pub fn apply_reward(&mut self, reward: Reward) {
+        match reward {
+            Reward::Take(card) => self.deck.push(card),
+            Reward::Remove(card) => {
+                if let Some(index) = self.deck.iter().position(|&c| c == card) {
+                    self.deck.remove(index);
+                }
+            }
+            Reward::Upgrade(card) => {
+                if let Some(slot) = self.deck.iter_mut().find(|c| **c == card) {
+                    *slot = card.upgraded().unwrap_or(card);
+                }
+            }
+            Reward::Skip => (),
         }
+        self.reward_pending = false;
     }
 }
 
+/// A change to the deck chosen between levels.
+#[derive(Clone, Copy, Debug)]
+pub enum Reward {
+    Take(Card),
+    Remove(Card),
+    Upgrade(Card),
+    Skip,
+}
+
+// the cells hit by the boss's slam
+fn slam_zone(world: &World, centre: Coord) -> impl '_ + Iterator<Item = Coord> {
+    Directions
+        .into_iter()
+        .map(move |direction| centre + direction.coord())
+        .filter(move |&coord| {
+            world.grid().get(coord).is_some_and(|cell| !cell.is_solid())
+        })
+}
+
+fn cardinal_direction_towards(from: Coord, to: Coord) -> CardinalDirection {
+    let delta = to - from;
+    if delta.x.abs() >= delta.y.abs() {
+        if delta.x > 0 {
+            CardinalDirection::East
+        } else {
+            CardinalDirection::West
+        }
+    } else if delta.y > 0 {
+        CardinalDirection::South
+    } else {
+        CardinalDirection::North
+    }
+}
+
+fn commit_boss_action(
+    pathfinding: &mut PathfindingContext,
+    world: &World,
+    id: EntityId,
+    player_coord: Coord,
+    turns_taken: u32,
+) {
+    let boss = world.entities().get(&id).unwrap();
+    let coord = boss.coord();
+    let phase = boss.boss_phase().unwrap();
+    if phase == BossPhase::Frenzy && turns_taken.is_multiple_of(SUMMON_PERIOD) {
+        if let Some(direction) = CardinalDirections.into_iter().find(|direction| {
+            let summon_coord = coord + direction.coord();
+            world.is_free_for_summon(summon_coord)
+                && !pathfinding.commitment_grid().is_committed(summon_coord)
+        }) {
+            pathfinding.commit_action_in_direction(
+                id,
+                coord,
+                direction,
+                CommitmentType::Summon,
+            );
+            return;
+        }
+    }
+    let delta = player_coord - coord;
+    let in_range = delta.x.abs() <= 1 && delta.y.abs() <= 1;
+    // slams alternate with regular attacks, leaving a turn to step out of the way
+    if phase != BossPhase::Pursuit && in_range && turns_taken.is_multiple_of(2) {
+        let cells = slam_zone(world, coord)
+            .map(|cell_coord| (cell_coord, cardinal_direction_towards(coord, cell_coord)))
+            .collect::<Vec<_>>();
+        pathfinding.commit_area(
+            id,
+            cardinal_direction_towards(coord, player_coord),
+            CommitmentType::Slam,
+            cells,
+        );
+    } else {
+        pathfinding.commit_action(id, world, CommitmentType::Move);
+    }
+}
+
+fn choose_character_upgrades<R: Rng>(
+    hand_size: usize,
+    amount: usize,
+    rng: &mut R,
+) -> impl Iterator<Item = &'static CharacterUpgrade> {
+    use CharacterUpgrade::*;
+    const WITH_HAND: &'static [CharacterUpgrade] = &[Life, Power, Hand, Vision];
+    const WITHOUT_HAND: &'static [CharacterUpgrade] = &[Life, Power, Vision];
+    let slice = if hand_size >= MAX_NUM_CARDS {
+        WITHOUT_HAND
+    } else {
+        WITH_HAND
+    };
+    slice.choose_multiple(rng, amount)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Objective {
+    ClearEnemies,
+    ReachStairsWithin { turns: u32 },
+    CollectKey,
+    NoHealing,
+}
+
+impl Objective {
+    // objectives which are about avoiding something are complete if they are still in
+    // progress when the player takes the stairs
+    fn complete_on_exit(self) -> bool {
+        match self {
+            Objective::ReachStairsWithin { .. } | Objective::NoHealing => true,
+            Objective::ClearEnemies | Objective::CollectKey => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectiveStatus {
+    InProgress,
+    Complete,
+    Failed,
+}
+
 pub enum End {
-    ExitLevel(BetweenLevels),
+    ExitLevel(Box<BetweenLevels>),
     PlayerDied,
     Victory,
 }
@@ -161,9 +677,12 @@ pub enum InteractiveType {
     Flame,
     Altar,
     Fountain,
+    Anvil,
+    Chest,
+    Merchant,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CharacterUpgrade {
     Life,
     Power,
@@ -171,6 +690,89 @@ pub enum CharacterUpgrade {
     Vision,
 }
 
+const LIFE_UPGRADE_HIT_POINTS: u32 = 2;
+const POWER_UPGRADE_DRAW_COUNTDOWN: u32 = 10;
+const VISION_UPGRADE_LIGHT_RANGE_SQUARED: u32 = 30;
+
+/// A passive item found in a chest, kept for the rest of the run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Relic {
+    /// The first card played from each hand costs no power.
+    Hourglass,
+    /// Spikes deal 2 damage to enemies.
+    Whetstone,
+    /// Heal 1 hit point when leaving a level.
+    Bandage,
+    /// Blink range is increased by 4.
+    Lodestone,
+}
+
+pub const ALL_RELICS: &[Relic] = &[
+    Relic::Hourglass,
+    Relic::Whetstone,
+    Relic::Bandage,
+    Relic::Lodestone,
+];
+
+/// A consumable picked up from the map and held until used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Item {
+    /// Deals 2 damage to every adjacent enemy.
+    Bomb,
+    /// Reveals the whole level on the map.
+    MappingScroll,
+    /// Leaves a bright light where it is used.
+    Torch,
+    /// Teleports to a random explored location out of sight.
+    TeleportCharm,
+}
+
+pub const ALL_ITEMS: &[Item] = &[
+    Item::Bomb,
+    Item::MappingScroll,
+    Item::Torch,
+    Item::TeleportCharm,
+];
+
+/// The most items that can be held at once.
+pub const MAX_ITEMS: usize = 4;
+
+const BOMB_DAMAGE: u32 = 2;
+
+impl Item {
+    pub(crate) fn foreground_tile(self) -> ForegroundTile {
+        match self {
+            Item::Bomb => ForegroundTile::Bomb,
+            Item::MappingScroll => ForegroundTile::MappingScroll,
+            Item::Torch => ForegroundTile::Torch,
+            Item::TeleportCharm => ForegroundTile::TeleportCharm,
+        }
+    }
+    fn from_foreground_tile(foreground_tile: ForegroundTile) -> Option<Self> {
+        match foreground_tile {
+            ForegroundTile::Bomb => Some(Item::Bomb),
+            ForegroundTile::MappingScroll => Some(Item::MappingScroll),
+            ForegroundTile::Torch => Some(Item::Torch),
+            ForegroundTile::TeleportCharm => Some(Item::TeleportCharm),
+            _ => None,
+        }
+    }
+}
+
+/// A path through the dungeon. Side branches lead off the main path for a single level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Branch {
+    Main,
+    /// Fewer enemies and elites, and more health potions.
+    Ice,
+    /// More enemies and elites, guarding extra upgrades, relics and items.
+    Ruins,
+}
+
+const RESHUFFLE_GLOW_FRAMES: u32 = 10;
+const WHETSTONE_SPIKE_DAMAGE: u32 = 2;
+const LODESTONE_BLINK_BONUS: u32 = 4;
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum InteractiveParam {
     Flame {
@@ -187,8 +789,40 @@ pub enum InteractiveParam {
         entity_id: EntityId,
         count: usize,
     },
+    Anvil {
+        card: Card,
+        entity_id: EntityId,
+    },
+    Chest {
+        entity_id: EntityId,
+    },
+    Merchant {
+        purchase: Purchase,
+        entity_id: EntityId,
+    },
+}
+
+/// Something bought from a merchant with gold.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Purchase {
+    Card(Card),
+    /// Pay to remove a card from the draw pile.
+    RemoveCard(Card),
+    CharacterUpgrade(CharacterUpgrade),
 }
 
+impl Purchase {
+    pub fn price(self) -> u32 {
+        match self {
+            Purchase::Card(_) => 15,
+            Purchase::RemoveCard(_) => 20,
+            Purchase::CharacterUpgrade(_) => 40,
+        }
+    }
+}
+
+const GOLD_PER_PILE: u32 = 5;
+
 pub enum Tick {
     End(End),
     CancelAction(CancelAction),
@@ -250,6 +884,34 @@ pub enum Card {
     Burn,
     Deposit,
     Caltrop,
+    Smash,
+    BumpPlus,
+    BlinkPlus,
+    HealPlus,
+    SparkPlus,
+    BlockPlus,
+    FreezePlus,
+    SpikePlus,
+    SurroundPlus,
+    ShredPlus,
+    DepositPlus,
+    CaltropPlus,
+    Poise,
+    Flicker,
+    Rampart,
+}
+
+/// Rules for where a card goes over its lifecycle, instead of the spent pile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Keyword {
+    /// Stays in hand when a new hand is drawn.
+    Retain,
+    /// Burnt when played.
+    Exhaust,
+    /// Always in the first hand of a level.
+    Innate,
+    /// Burnt if still in hand when a new hand is drawn.
+    Ethereal,
 }
 
 const NEGATIVE_CARDS: &'static [Card] = &[Card::Clog, Card::Parasite, Card::Drain];
@@ -274,6 +936,10 @@ const POSITIVE_CARDS: &'static [Card] = &[
     Card::Burn,
     Card::Deposit,
     Card::Caltrop,
+    Card::Smash,
+    Card::Poise,
+    Card::Flicker,
+    Card::Rampart,
 ];
 
 impl Card {
@@ -293,6 +959,7 @@ impl Card {
             Card::Garden => 20,
             Card::Armour => 20,
             Card::Bash => 10,
+            Card::Smash => 20,
             Card::Burn => 99,
             Card::Blast => 30,
             Card::Surround => 30,
@@ -302,8 +969,67 @@ impl Card {
             Card::Parasite => 10,
             Card::Clog => 10,
             Card::Drain => 40,
+            Card::BumpPlus => 10,
+            Card::BlinkPlus => 20,
+            Card::HealPlus => 5,
+            Card::SparkPlus => 5,
+            Card::BlockPlus => 5,
+            Card::FreezePlus => 10,
+            Card::SpikePlus => 5,
+            Card::SurroundPlus => 30,
+            Card::ShredPlus => 40,
+            Card::DepositPlus => 10,
+            Card::CaltropPlus => 10,
+            Card::Poise => 10,
+            Card::Flicker => 10,
+            Card::Rampart => 20,
+        }
+    }
+    pub fn keywords(self) -> &'static [Keyword] {
+        match self {
+            Card::Parasite | Card::Drain => &[Keyword::Exhaust],
+            Card::Poise => &[Keyword::Retain],
+            Card::Rampart => &[Keyword::Innate],
+            Card::Flicker => &[Keyword::Ethereal],
+            _ => &[],
+        }
+    }
+    pub fn has_keyword(self, keyword: Keyword) -> bool {
+        self.keywords().contains(&keyword)
+    }
+    /// The next tier of this card, as made by an anvil or the reward screen.
+    pub fn upgraded(self) -> Option<Card> {
+        match self {
+            Card::Bump => Some(Card::BumpPlus),
+            Card::Blink => Some(Card::BlinkPlus),
+            Card::Heal => Some(Card::HealPlus),
+            Card::Spark => Some(Card::SparkPlus),
+            Card::Block => Some(Card::BlockPlus),
+            Card::Freeze => Some(Card::FreezePlus),
+            Card::Spike => Some(Card::SpikePlus),
+            Card::Surround => Some(Card::SurroundPlus),
+            Card::Shred => Some(Card::ShredPlus),
+            Card::Deposit => Some(Card::DepositPlus),
+            Card::Caltrop => Some(Card::CaltropPlus),
+            _ => None,
         }
     }
+    pub fn is_upgraded(self) -> bool {
+        matches!(
+            self,
+            Card::BumpPlus
+                | Card::BlinkPlus
+                | Card::HealPlus
+                | Card::SparkPlus
+                | Card::BlockPlus
+                | Card::FreezePlus
+                | Card::SpikePlus
+                | Card::SurroundPlus
+                | Card::ShredPlus
+                | Card::DepositPlus
+                | Card::CaltropPlus
+        )
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -493,6 +1219,7 @@ impl Gws {
         between_levels: Option<BetweenLevels>,
         rng: &mut R,
         debug_terrain_string: Option<&str>,
+        wfc_sample: Option<&WfcSample>,
     ) -> Self {
         let BetweenLevels {
             player,
@@ -501,30 +1228,93 @@ impl Gws {
             hand_size,
             max_draw_countdown,
             next_level,
-        } = between_levels.unwrap_or_else(BetweenLevels::initial);
-        let terrain::TerrainDescription {
-            size,
-            player_coord,
-            instructions,
-        } = match TERRAIN_CHOICE {
-            TerrainChoice::StringDemo => terrain::from_str(
-                debug_terrain_string.unwrap_or(include_str!("terrain_string.txt")),
-                rng,
-            ),
-            TerrainChoice::WfcIceCave(size) => terrain::wfc(size, next_level, rng),
+            bonus_upgrades: _,
+            reward_pending: _,
+            settings,
+            relics,
+            items,
+            gold,
+            route,
+            mut stored_levels,
+            arrival,
+        } = between_levels.unwrap_or_else(|| BetweenLevels::new_game(Default::default()));
+        let branch = *route.last().unwrap();
+        let stored_level = stored_levels
+            .iter()
+            .position(|stored_level| {
+                stored_level.level == next_level && stored_level.branch == branch
+            })
+            .map(|index| stored_levels.remove(index));
+        let (stored_level, player_coord) = match stored_level {
+            Some(stored_level) => {
+                let player_coord = stored_level.arrival_coord(arrival);
+                (stored_level, player_coord)
+            }
+            None => {
+                let terrain::TerrainDescription {
+                    size,
+                    player_coord,
+                    instructions,
+                    objective,
+                } = match TERRAIN_CHOICE {
+                    TerrainChoice::StringDemo => terrain::from_str(
+                        debug_terrain_string
+                            .unwrap_or(include_str!("terrain_string.txt")),
+                        rng,
+                    ),
+                    TerrainChoice::WfcIceCave(size) => {
+                        terrain::wfc(size, next_level, branch, &settings, wfc_sample, rng)
+                    }
+                };
+                let mut world = World::new(size);
+                for instruction in instructions {
+                    world.interpret_instruction(settings.mutate_instruction(instruction));
+                }
+                let boss_id = world
+                    .entities()
+                    .values()
+                    .find(|entity| entity.is_boss())
+                    .map(Entity::id);
+                let stored_level = StoredLevel {
+                    level: next_level,
+                    branch,
+                    world,
+                    visible_area: VisibileArea::new(size),
+                    objective,
+                    turns_taken: 0,
+                    healing_used: false,
+                    boss_id,
+                    // a forgotten level made again on the way back up has already paid out
+                    rewards_paid: arrival != Arrival::Descending,
+                    start_coord: player_coord,
+                };
+                (stored_level, player_coord)
+            }
         };
+        let StoredLevel {
+            mut world,
+            visible_area,
+            objective,
+            turns_taken,
+            healing_used,
+            boss_id,
+            rewards_paid,
+            start_coord,
+            ..
+        } = stored_level;
+        let size = world.grid().size();
         deck.shuffle(rng);
+        // the hand is drawn from the end of the deck
+        deck.sort_by_key(|card| card.has_keyword(Keyword::Innate));
         let draw_countdown = DrawCountdown {
             max: max_draw_countdown,
             current: max_draw_countdown,
         };
-        let mut world = World::new(size);
         let hand = (0..hand_size).map(|_| None).collect::<Vec<_>>();
-        for instruction in instructions {
-            world.interpret_instruction(instruction);
-        }
         let player_id = world.add_entity(player_coord, player);
-        let visible_area = VisibileArea::new(size);
+        if relics.contains(&Relic::Whetstone) {
+            world.set_npc_spike_damage(WHETSTONE_SPIKE_DAMAGE);
+        }
         let pathfinding = PathfindingContext::new(size);
         let mut s = Self {
             world,
@@ -540,9 +1330,26 @@ impl Gws {
             waste: Vec::new(),
             burnt,
             level: next_level,
+            objective,
+            turns_taken,
+            healing_used,
+            boss_id,
+            settings,
+            relics,
+            card_played_this_hand: false,
+            mulligan_used_this_hand: false,
+            items,
+            gold,
+            route,
+            stored_levels,
+            reshuffles: 0,
+            rewards_paid,
+            start_coord,
+            items_left_behind: 0,
+            item_left_behind_at: None,
         };
         s.engine_commit();
-        s.draw_hand();
+        s.draw_hand(rng);
         s.update_visible_area();
         s
     }
@@ -551,6 +1358,69 @@ impl Gws {
         &self.draw_countdown
     }
 
+    /// True when the next call to `tick` will act on an input.
+    pub fn is_awaiting_input(&self) -> bool {
+        self.animation.is_empty() && self.turn == Turn::Player
+    }
+
+    /// The first step along the shortest path to `coord` through discovered cells.
+    pub fn travel_direction(&mut self, coord: Coord) -> Option<CardinalDirection> {
+        let player_coord = self.player().coord();
+        self.pathfinding.travel_direction(
+            &self.world,
+            &self.visible_area,
+            player_coord,
+            coord,
+        )
+    }
+
+    /// The first step towards the nearest discovered cell bordering unexplored territory.
+    pub fn explore_direction(&mut self) -> Option<CardinalDirection> {
+        let player_coord = self.player().coord();
+        self.pathfinding
+            .explore_direction(&self.world, &self.visible_area, player_coord)
+    }
+
+    /// The location of the stairs (or the end of the dungeon) if it has been discovered.
+    pub fn discovered_exit_coord(&self) -> Option<Coord> {
+        self.world
+            .entities()
+            .values()
+            .map(Entity::coord)
+            .find(|&coord| {
+                self.visible_area.is_discovered(coord)
+                    && self.world.grid().get(coord).is_some_and(|cell| {
+                        cell.is_end()
+                            || cell.entity_iter(self.world.entities()).any(|entity| {
+                                entity.foreground_tile() == Some(ForegroundTile::Stairs)
+                            })
+                    })
+            })
+    }
+
+    /// What the npc will do at the end of the player's next turn.
+    pub fn committed_action(
+        &self,
+        id: EntityId,
+    ) -> Option<(CardinalDirection, CommitmentType)> {
+        self.pathfinding.committed_action(id)
+    }
+
+    /// What a hasted npc will do straight after its committed action.
+    pub fn followup_action(
+        &self,
+        id: EntityId,
+    ) -> Option<(CardinalDirection, CommitmentType)> {
+        self.pathfinding.followup_action(id)
+    }
+
+    pub fn visible_npc_ids(&self) -> impl '_ + Iterator<Item = EntityId> {
+        self.world.npc_ids().cloned().filter(move |id| {
+            let coord = self.world.entities().get(id).unwrap().coord();
+            self.visible_area.is_visible(coord)
+        })
+    }
+
     fn player_turn<R: Rng>(
         &mut self,
         input: Input,
@@ -569,6 +1439,64 @@ impl Gws {
                     self.world.deal_damage(entity_id, 1);
                     (Ok(ApplyAction::Done), 0)
                 }
+                InteractiveParam::Anvil { card, entity_id } => {
+                    let slot = self
+                        .deck
+                        .iter_mut()
+                        .find(|c| **c == card)
+                        .expect("no such card in deck");
+                    *slot = card.upgraded().expect("card can't be upgraded");
+                    self.world.deal_damage(entity_id, 1);
+                    (Ok(ApplyAction::Done), 0)
+                }
+                InteractiveParam::Merchant {
+                    purchase,
+                    entity_id,
+                } => {
+                    if purchase.price() > self.gold {
+                        return Err(CancelAction::NotEnoughGold);
+                    }
+                    match purchase {
+                        Purchase::Card(card) => {
+                            self.deck.push(card);
+                            self.deck.shuffle(rng);
+                        }
+                        Purchase::RemoveCard(card) => {
+                            let index = self
+                                .deck
+                                .iter()
+                                .position(|&c| c == card)
+                                .ok_or(CancelAction::InvalidCard)?;
+                            self.deck.remove(index);
+                        }
+                        Purchase::CharacterUpgrade(CharacterUpgrade::Hand)
+                            if !self.can_upgrade_hand() =>
+                        {
+                            return Err(CancelAction::HandFull);
+                        }
+                        Purchase::CharacterUpgrade(character_upgrade) => {
+                            self.apply_character_upgrade(character_upgrade);
+                        }
+                    }
+                    self.gold -= purchase.price();
+                    self.world.remove_from_stock(entity_id, purchase);
+                    (Ok(ApplyAction::Done), 0)
+                }
+                InteractiveParam::Chest { entity_id } => {
+                    let unfound = ALL_RELICS
+                        .iter()
+                        .cloned()
+                        .filter(|relic| !self.relics.contains(relic))
+                        .collect::<Vec<_>>();
+                    if let Some(&relic) = unfound.choose(rng) {
+                        if relic == Relic::Whetstone {
+                            self.world.set_npc_spike_damage(WHETSTONE_SPIKE_DAMAGE);
+                        }
+                        self.relics.push(relic);
+                    }
+                    self.world.deal_damage(entity_id, 1);
+                    (Ok(ApplyAction::Done), 0)
+                }
                 InteractiveParam::Fountain {
                     card,
                     entity_id,
@@ -586,13 +1514,7 @@ impl Gws {
                     entity_id,
                     card,
                 } => {
-                    use CharacterUpgrade::*;
-                    match character_upgrade {
-                        Life => self.world.increase_max_hit_points(self.player_id, 2),
-                        Power => self.draw_countdown.max += 10,
-                        Hand => self.hand.push(None),
-                        Vision => self.world.increase_light_radius(self.player_id, 30),
-                    }
+                    self.apply_character_upgrade(character_upgrade);
                     self.deck.push(card);
                     self.deck.shuffle(rng);
                     self.world.deal_damage(entity_id, 1);
@@ -600,12 +1522,67 @@ impl Gws {
                 }
             },
             Input::Move(direction) => {
+                let destination = self.player().coord() + direction.coord();
+                if self.is_end_guarded()
+                    && self
+                        .world
+                        .grid()
+                        .get(destination)
+                        .is_some_and(WorldCell::is_end)
+                {
+                    return Err(CancelAction::EndGuarded);
+                }
+                let cost = if self.player_cell().is_water() {
+                    WATER_MOVE_COST
+                } else {
+                    1
+                };
                 let result = self.world.move_entity_in_direction_with_attack_policy(
                     self.player_id,
                     direction,
                 );
+                (result, cost)
+            }
+            Input::CloseDoor(direction) => (
+                self.world
+                    .close_door_in_direction(self.player_id, direction),
+                1,
+            ),
+            Input::Wait => (Ok(ApplyAction::Done), 1),
+            Input::UseItem(item) => {
+                let index = self
+                    .items
+                    .iter()
+                    .position(|&i| i == item)
+                    .ok_or(CancelAction::NoSuchItem)?;
+                let result = self.use_item(item, rng);
+                if result.is_ok() {
+                    self.items.remove(index);
+                }
                 (result, 1)
             }
+            Input::Mulligan(slots) => {
+                if self.mulligan_used_this_hand {
+                    return Err(CancelAction::MulliganUsed);
+                }
+                if MULLIGAN_COST > self.draw_countdown.current {
+                    return Err(CancelAction::NotEnoughEnergy);
+                }
+                let mut discarded = false;
+                // slots outside the hand are ignored
+                for slot in slots {
+                    if let Some(card) = self.hand.get_mut(slot).and_then(Option::take) {
+                        self.waste.push(card);
+                        discarded = true;
+                    }
+                }
+                if !discarded {
+                    return Err(CancelAction::InvalidCard);
+                }
+                self.fill_hand(rng);
+                self.mulligan_used_this_hand = true;
+                (Ok(ApplyAction::Done), MULLIGAN_COST)
+            }
             Input::PlayCard { slot, param } => {
                 let card = if let Some(&card) = self.hand.get(slot) {
                     card
@@ -617,94 +1594,131 @@ impl Gws {
                 } else {
                     return Err(CancelAction::InvalidCard);
                 };
-                if card.cost() > self.draw_countdown.current {
+                let cost = self.card_cost(card);
+                if cost > self.draw_countdown.current {
                     return Err(CancelAction::NotEnoughEnergy);
                 }
-                enum Deck {
-                    Spent,
-                    Burnt,
-                    AllToDeck,
-                    AllToSpent,
-                    AllToBurnt,
-                }
-                use Deck::*;
-                let (result, deck) = match (card, param) {
-                    (Card::Blink, CardParam::Coord(coord)) => (self.blink(coord), Spent),
+                let result = match (card, param) {
+                    (Card::Blink, CardParam::Coord(coord)) => {
+                        self.blink(coord, World::BLINK_RANGE + self.blink_bonus())
+                    }
+                    (Card::Flicker, CardParam::Coord(coord)) => {
+                        self.blink(coord, World::BLINK_RANGE + self.blink_bonus())
+                    }
+                    (Card::BlinkPlus, CardParam::Coord(coord)) => {
+                        self.blink(coord, BLINK_PLUS_RANGE + self.blink_bonus())
+                    }
                     (Card::Bump, CardParam::CardinalDirection(direction)) => {
-                        (self.bump(direction), Spent)
+                        self.bump(direction, 2)
+                    }
+                    (Card::BumpPlus, CardParam::CardinalDirection(direction)) => {
+                        self.bump(direction, 3)
                     }
                     (Card::Bash, CardParam::CardinalDirection(direction)) => {
-                        (self.bash(direction), Spent)
+                        self.bash(direction)
                     }
-                    (Card::Spark, CardParam::CardinalDirection(direction)) => {
-                        (self.spark(direction), Spent)
+                    (Card::Smash, CardParam::CardinalDirection(direction)) => {
+                        self.smash(direction)
+                    }
+                    (
+                        Card::Spark | Card::SparkPlus,
+                        CardParam::CardinalDirection(direction),
+                    ) => self.spark(direction),
+                    (Card::Heal | Card::HealPlus | Card::Poise, CardParam::Confirm) => {
+                        self.heal(1)
                     }
-                    (Card::Heal, CardParam::Confirm) => (self.heal(1), Spent),
                     (Card::Empower, CardParam::Confirm) => {
                         // TODO hacky
                         self.draw_countdown.current = (self.draw_countdown.current + 40)
                             .min(self.draw_countdown.max + Card::Empower.cost());
-                        (Ok(ApplyAction::Done), Spent)
+                        Ok(ApplyAction::Done)
                     }
                     // TODO easier healing to full
-                    (Card::Recover, CardParam::Confirm) => {
-                        (self.heal(::std::u32::MAX), Spent)
-                    }
+                    (Card::Recover, CardParam::Confirm) => self.heal(::std::u32::MAX),
                     (Card::Save, CardParam::Confirm) => {
-                        (Ok(ApplyAction::Done), AllToDeck)
+                        let rest = self.take_rest_of_hand(slot);
+                        self.deck.extend(rest);
+                        self.deck.shuffle(rng);
+                        Ok(ApplyAction::Done)
                     }
                     (Card::Spend, CardParam::Confirm) => {
-                        (Ok(ApplyAction::Done), AllToSpent)
+                        let rest = self.take_rest_of_hand(slot);
+                        self.spent.extend(rest);
+                        Ok(ApplyAction::Done)
                     }
                     (Card::Burn, CardParam::Confirm) => {
-                        (Ok(ApplyAction::Done), AllToBurnt)
+                        let rest = self.take_rest_of_hand(slot);
+                        self.burnt.extend(rest);
+                        Ok(ApplyAction::Done)
                     }
-                    (Card::Clog, CardParam::Confirm) => (Ok(ApplyAction::Done), Spent),
+                    (Card::Clog, CardParam::Confirm) => Ok(ApplyAction::Done),
                     (Card::Parasite, CardParam::Confirm) => {
                         self.world.deal_damage(self.player_id, 2);
-                        (Ok(ApplyAction::Done), Burnt)
+                        Ok(ApplyAction::Done)
                     }
-                    (Card::Drain, CardParam::Confirm) => (Ok(ApplyAction::Done), Burnt),
-                    (Card::Deposit, CardParam::CardinalDirection(direction)) => {
+                    (Card::Drain, CardParam::Confirm) => Ok(ApplyAction::Done),
+                    (
+                        Card::Deposit | Card::DepositPlus,
+                        CardParam::CardinalDirection(direction),
+                    ) => {
                         let player_coord = self.player().coord();
-                        let result = self
-                            .world
+                        self.world
                             .move_entity_in_direction_with_attack_policy(
                                 self.player_id,
                                 direction,
                             )
-                            .and_then(|_| self.block(player_coord));
-                        (result, Spent)
+                            .and_then(|_| self.block(player_coord))
                     }
-                    (Card::Caltrop, CardParam::CardinalDirection(direction)) => {
+                    (
+                        Card::Caltrop | Card::CaltropPlus,
+                        CardParam::CardinalDirection(direction),
+                    ) => {
                         let player_coord = self.player().coord();
-                        let result = self
-                            .world
+                        self.world
                             .move_entity_in_direction_with_attack_policy(
                                 self.player_id,
                                 direction,
                             )
-                            .and_then(|_| self.spike(player_coord));
-                        (result, Spent)
+                            .and_then(|_| self.spike(player_coord))
                     }
 
-                    (Card::Block, CardParam::Coord(coord)) => (self.block(coord), Spent),
+                    (Card::Block | Card::BlockPlus, CardParam::Coord(coord)) => {
+                        self.block(coord)
+                    }
                     (Card::Surround, CardParam::Coord(coord)) => {
                         for d in CardinalDirections {
                             let _ = self.block(coord + d.coord());
                         }
-                        (Ok(ApplyAction::Done), Spent)
+                        Ok(ApplyAction::Done)
+                    }
+                    (Card::SurroundPlus, CardParam::Coord(coord)) => {
+                        for d in Directions {
+                            let _ = self.block(coord + d.coord());
+                        }
+                        Ok(ApplyAction::Done)
                     }
                     (Card::Freeze, CardParam::Coord(coord)) => {
-                        (self.freeze(coord), Spent)
+                        self.freeze(coord, FREEZE_TURNS)
+                    }
+                    (Card::FreezePlus, CardParam::Coord(coord)) => {
+                        self.freeze(coord, FREEZE_PLUS_TURNS)
+                    }
+                    (Card::Spike | Card::SpikePlus, CardParam::Coord(coord)) => {
+                        self.spike(coord)
                     }
-                    (Card::Spike, CardParam::Coord(coord)) => (self.spike(coord), Spent),
                     (Card::Shred, CardParam::Coord(coord)) => {
                         let _ = self.spike(coord);
                         for d in CardinalDirections {
                             let _ = self.spike(coord + d.coord());
                         }
-                        (Ok(ApplyAction::Done), Spent)
+                        Ok(ApplyAction::Done)
+                    }
+                    (Card::ShredPlus, CardParam::Coord(coord)) => {
+                        let _ = self.spike(coord);
+                        for d in Directions {
+                            let _ = self.spike(coord + d.coord());
+                        }
+                        Ok(ApplyAction::Done)
                     }
                     (Card::Garden, CardParam::Confirm) => {
                         let coord =
@@ -712,15 +1726,15 @@ impl Gws {
                         for d in CardinalDirections {
                             let _ = self.spike(coord + d.coord());
                         }
-                        (Ok(ApplyAction::Done), Spent)
+                        Ok(ApplyAction::Done)
                     }
-                    (Card::Armour, CardParam::Confirm) => {
+                    (Card::Armour | Card::Rampart, CardParam::Confirm) => {
                         let coord =
                             self.world.entities().get(&self.player_id).unwrap().coord();
                         for d in CardinalDirections {
                             let _ = self.block(coord + d.coord());
                         }
-                        (Ok(ApplyAction::Done), Spent)
+                        Ok(ApplyAction::Done)
                     }
                     (Card::Blast, CardParam::Confirm) => {
                         let mut animations = Vec::new();
@@ -733,50 +1747,29 @@ impl Gws {
                             }
                         }
                         if animations.is_empty() {
-                            (Err(CancelAction::LocationBlocked), Spent)
+                            Err(CancelAction::LocationBlocked)
                         } else {
-                            (Ok(ApplyAction::MultiAnimation(animations)), Spent)
+                            Ok(ApplyAction::MultiAnimation(animations))
                         }
                     }
                     _ => return Err(CancelAction::InvalidCard),
                 };
                 if result.is_ok() {
+                    if matches!(
+                        card,
+                        Card::Heal | Card::HealPlus | Card::Poise | Card::Recover
+                    ) {
+                        self.healing_used = true;
+                    }
                     self.hand[slot] = None;
-                    match deck {
-                        Spent => self.spent.push(card),
-                        Burnt => self.burnt.push(card),
-                        // TODO duplication
-                        AllToDeck => {
-                            self.spent.push(card);
-                            for slot in self.hand.iter_mut() {
-                                if let Some(card) = *slot {
-                                    self.deck.push(card);
-                                    *slot = None;
-                                }
-                            }
-                            self.deck.shuffle(rng);
-                        }
-                        AllToSpent => {
-                            self.spent.push(card);
-                            for slot in self.hand.iter_mut() {
-                                if let Some(card) = *slot {
-                                    self.spent.push(card);
-                                    *slot = None;
-                                }
-                            }
-                        }
-                        AllToBurnt => {
-                            self.spent.push(card);
-                            for slot in self.hand.iter_mut() {
-                                if let Some(card) = *slot {
-                                    self.burnt.push(card);
-                                    *slot = None;
-                                }
-                            }
-                        }
+                    self.card_played_this_hand = true;
+                    if card.has_keyword(Keyword::Exhaust) {
+                        self.burnt.push(card);
+                    } else {
+                        self.spent.push(card);
                     }
                 }
-                (result, card.cost())
+                (result, cost)
             }
         };
         // TODO this is messy
@@ -791,7 +1784,7 @@ impl Gws {
                     true
                 };
                 if should_draw {
-                    self.draw_hand();
+                    self.draw_hand(rng);
                     self.draw_countdown.current = self.draw_countdown.max;
                 } else {
                     self.draw_countdown.current -= cost;
@@ -801,13 +1794,185 @@ impl Gws {
         result
     }
 
-    fn draw_hand(&mut self) {
+    fn apply_character_upgrade(&mut self, character_upgrade: CharacterUpgrade) {
+        use CharacterUpgrade::*;
+        match character_upgrade {
+            Life => self
+                .world
+                .increase_max_hit_points(self.player_id, LIFE_UPGRADE_HIT_POINTS),
+            Power => self.draw_countdown.max += POWER_UPGRADE_DRAW_COUNTDOWN,
+            Hand => self.hand.push(None),
+            Vision => self.world.increase_light_radius(
+                self.player_id,
+                VISION_UPGRADE_LIGHT_RANGE_SQUARED,
+            ),
+        }
+    }
+
+    fn pick_up_gold(&mut self) {
+        let piles = self
+            .player_cell()
+            .entity_iter(self.world.entities())
+            .filter(|entity| entity.foreground_tile() == Some(ForegroundTile::Gold))
+            .map(Entity::id)
+            .collect::<Vec<_>>();
+        for id in piles {
+            self.world.remove_entity(id);
+            self.gold += GOLD_PER_PILE;
+        }
+    }
+
+    fn pick_up_item(&mut self) {
+        let item = self
+            .player_cell()
+            .entity_iter(self.world.entities())
+            .find_map(|entity| {
+                entity
+                    .foreground_tile()
+                    .and_then(Item::from_foreground_tile)
+                    .map(|item| (entity.id(), item))
+            });
+        let player_coord = self.player().coord();
+        match item {
+            Some(_) if self.items.len() >= MAX_ITEMS => {
+                if self.item_left_behind_at != Some(player_coord) {
+                    self.items_left_behind += 1;
+                    self.item_left_behind_at = Some(player_coord);
+                }
+            }
+            Some((id, item)) => {
+                self.world.remove_entity(id);
+                self.items.push(item);
+                self.item_left_behind_at = None;
+            }
+            None => self.item_left_behind_at = None,
+        }
+    }
+
+    fn use_item<R: Rng>(
+        &mut self,
+        item: Item,
+        rng: &mut R,
+    ) -> Result<ApplyAction, CancelAction> {
+        let player_coord = self.player().coord();
+        match item {
+            Item::Bomb => {
+                let to_damage = Directions
+                    .into_iter()
+                    .filter_map(|d| self.world.grid().get(player_coord + d.coord()))
+                    .flat_map(|cell| cell.entity_iter(self.world.entities()))
+                    .filter(|entity| entity.is_npc())
+                    .map(Entity::id)
+                    .collect::<Vec<_>>();
+                for id in to_damage {
+                    self.world.deal_damage(id, BOMB_DAMAGE);
+                }
+                Ok(ApplyAction::Done)
+            }
+            Item::MappingScroll => {
+                self.visible_area.discover_all();
+                Ok(ApplyAction::Done)
+            }
+            Item::Torch => {
+                self.world.add_entity(player_coord, PackedEntity::torch());
+                Ok(ApplyAction::Done)
+            }
+            Item::TeleportCharm => {
+                let candidates = self
+                    .world
+                    .grid()
+                    .coord_iter()
+                    .filter(|&coord| {
+                        self.visible_area.is_discovered(coord)
+                            && !self.visible_area.is_visible(coord)
+                            && self.world.is_free_for_summon(coord)
+                            && self
+                                .world
+                                .grid()
+                                .get_checked(coord)
+                                .entity_iter(self.world.entities())
+                                .next()
+                                .is_none()
+                    })
+                    .collect::<Vec<_>>();
+                let &coord = candidates
+                    .choose(rng)
+                    .ok_or(CancelAction::NoTeleportDestination)?;
+                self.world
+                    .blink_entity_to_coord(self.player_id, coord, u32::MAX)
+            }
+        }
+    }
+
+    fn draw_hand<R: Rng>(&mut self, rng: &mut R) {
+        self.card_played_this_hand = false;
+        self.mulligan_used_this_hand = false;
         for slot in self.hand.iter_mut() {
-            if let Some(card) = *slot {
-                self.waste.push(card);
+            match *slot {
+                Some(card) if card.has_keyword(Keyword::Retain) => (),
+                Some(card) if card.has_keyword(Keyword::Ethereal) => {
+                    self.burnt.push(card);
+                    *slot = None;
+                }
+                Some(card) => {
+                    self.waste.push(card);
+                    *slot = None;
+                }
+                None => (),
             }
-            *slot = self.deck.pop();
         }
+        self.fill_hand(rng);
+    }
+
+    /// Draws into every empty hand slot.
+    fn fill_hand<R: Rng>(&mut self, rng: &mut R) {
+        for index in 0..self.hand.len() {
+            if self.hand[index].is_some() {
+                continue;
+            }
+            if self.deck.is_empty() && !self.reshuffle(rng) {
+                break;
+            }
+            self.hand[index] = self.deck.pop();
+        }
+    }
+
+    /// Empties every hand slot except `slot`.
+    fn take_rest_of_hand(&mut self, slot: usize) -> Vec<Card> {
+        self.hand
+            .iter_mut()
+            .enumerate()
+            .filter(|&(index, _)| index != slot)
+            .filter_map(|(_, card)| card.take())
+            .collect()
+    }
+
+    /// Returns false if the run's rule leaves nothing to shuffle back into the deck.
+    fn reshuffle<R: Rng>(&mut self, rng: &mut R) -> bool {
+        let rule = self.settings.reshuffle;
+        match rule {
+            ReshuffleRule::Never => return false,
+            ReshuffleRule::Waste => (),
+            ReshuffleRule::SpentAndWaste | ReshuffleRule::Fatigue => {
+                self.deck.append(&mut self.spent)
+            }
+        }
+        self.deck.append(&mut self.waste);
+        if self.deck.is_empty() {
+            return false;
+        }
+        self.deck.shuffle(rng);
+        self.reshuffles += 1;
+        let player_coord = self.player().coord();
+        let glow = self
+            .world
+            .add_entity(player_coord, PackedEntity::glow(rgb24(0, 200, 255)));
+        self.animation
+            .push(Animation::glow_fade_out(glow, RESHUFFLE_GLOW_FRAMES));
+        if rule == ReshuffleRule::Fatigue {
+            self.world.deal_damage(self.player_id, self.reshuffles);
+        }
+        true
     }
 
     fn spike(&mut self, coord: Coord) -> Result<ApplyAction, CancelAction> {
@@ -815,7 +1980,7 @@ impl Gws {
             && self.visible_area.light_colour(coord) != grey24(0)
         {
             if let Some(cell) = self.world.grid().get(coord) {
-                if cell.contains_npc() || cell.is_solid() {
+                if cell.contains_npc() || cell.is_solid() || cell.is_chasm() {
                     Err(CancelAction::LocationBlocked)
                 } else {
                     self.world.add_entity(coord, PackedEntity::spike());
@@ -829,7 +1994,7 @@ impl Gws {
         }
     }
 
-    fn freeze(&mut self, coord: Coord) -> Result<ApplyAction, CancelAction> {
+    fn freeze(&mut self, coord: Coord, turns: u32) -> Result<ApplyAction, CancelAction> {
         if self.visible_area.is_visible(coord)
             && self.visible_area.light_colour(coord) != grey24(0)
         {
@@ -839,7 +2004,7 @@ impl Gws {
                         .entity_iter(&self.world.entities())
                         .find_map(|e| if e.is_npc() { Some(e.id()) } else { None })
                         .unwrap();
-                    self.world.freeze_entity(id, 8);
+                    self.world.freeze_entity(id, turns);
                     Ok(ApplyAction::Done)
                 } else {
                     Err(CancelAction::NoEntity)
@@ -857,7 +2022,11 @@ impl Gws {
             && self.visible_area.light_colour(coord) != grey24(0)
         {
             if let Some(cell) = self.world.grid().get(coord) {
-                if cell.contains_npc() || cell.is_solid() || cell.contains_player() {
+                if cell.contains_npc()
+                    || cell.is_solid()
+                    || cell.is_chasm()
+                    || cell.contains_player()
+                {
                     Err(CancelAction::LocationBlocked)
                 } else {
                     self.world.add_entity(coord, PackedEntity::block());
@@ -870,11 +2039,12 @@ impl Gws {
             Err(CancelAction::DestinationNotVisible)
         }
     }
-    fn blink(&mut self, coord: Coord) -> Result<ApplyAction, CancelAction> {
+    fn blink(&mut self, coord: Coord, range: u32) -> Result<ApplyAction, CancelAction> {
         if self.visible_area.is_visible(coord)
             && self.visible_area.light_colour(coord) != grey24(0)
         {
-            self.world.blink_entity_to_coord(self.player_id, coord)
+            self.world
+                .blink_entity_to_coord(self.player_id, coord, range)
         } else {
             Err(CancelAction::DestinationNotVisible)
         }
@@ -883,8 +2053,10 @@ impl Gws {
     fn bump(
         &mut self,
         direction: CardinalDirection,
+        damage: u32,
     ) -> Result<ApplyAction, CancelAction> {
-        self.world.bump_npc_in_direction(self.player_id, direction)
+        self.world
+            .bump_npc_in_direction(self.player_id, direction, damage)
     }
 
     fn bash(
@@ -894,6 +2066,13 @@ impl Gws {
         self.world.bash_npc_in_direction(self.player_id, direction)
     }
 
+    fn smash(
+        &mut self,
+        direction: CardinalDirection,
+    ) -> Result<ApplyAction, CancelAction> {
+        self.world.smash_in_direction(self.player_id, direction)
+    }
+
     fn heal(&mut self, by: u32) -> Result<ApplyAction, CancelAction> {
         self.world.heal(self.player_id, by)
     }
@@ -906,6 +2085,7 @@ impl Gws {
     }
 
     fn engine_turn(&mut self) {
+        self.turns_taken += 1;
         self.world.reduce_remaining_turns();
         let mut unfreeze = Vec::new();
         for &id in self.world.npc_ids() {
@@ -918,7 +2098,40 @@ impl Gws {
         for id in unfreeze {
             self.world.reduce_freeze(id);
         }
-        for &(id, direction, typ) in self.pathfinding.committed_actions().iter() {
+        self.act_on_commitments(false);
+        // hasted npcs then take the second action they committed to
+        self.pathfinding.drop_stale_followups(&self.world);
+        self.act_on_commitments(true);
+        if self.turns_taken.is_multiple_of(REGENERATION_PERIOD) {
+            let to_regenerate = self
+                .world
+                .npc_ids()
+                .filter(|id| {
+                    self.world
+                        .entities()
+                        .get(id)
+                        .unwrap()
+                        .has_elite_trait(EliteTrait::Regenerating)
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            for id in to_regenerate {
+                let _ = self.world.heal(id, 1);
+            }
+        }
+        self.pathfinding.clear_commitments();
+        let npc_ids = self.world.npc_ids().cloned().collect::<Vec<_>>();
+        for id in npc_ids {
+            self.world.update_wading(id);
+        }
+    }
+    fn act_on_commitments(&mut self, followups: bool) {
+        let actions = if followups {
+            self.pathfinding.followup_actions().collect::<Vec<_>>()
+        } else {
+            self.pathfinding.committed_actions().to_vec()
+        };
+        for (id, direction, typ) in actions {
             if let Some(entity) = self.world.entities().get(&id) {
                 if entity.is_frozen() {
                     continue;
@@ -968,6 +2181,31 @@ impl Gws {
                     CommitmentType::Heal(count) => {
                         self.world.set_heal_countdown(id, Some(count))
                     }
+                    CommitmentType::Slam => {
+                        let coord = entity.coord();
+                        let player_coord = self.player().coord();
+                        let glow = self
+                            .world
+                            .add_entity(coord, PackedEntity::glow(rgb24(255, 40, 0)));
+                        self.animation.push(Animation::glow_fade_out(glow, 10));
+                        if slam_zone(&self.world, coord).any(|c| c == player_coord) {
+                            Ok(ApplyAction::Animation(Animation::damage(
+                                self.player_id,
+                                cardinal_direction_towards(coord, player_coord),
+                                SLAM_DAMAGE,
+                            )))
+                        } else {
+                            Ok(ApplyAction::Done)
+                        }
+                    }
+                    CommitmentType::Summon => {
+                        let coord = entity.coord() + direction.coord();
+                        if self.world.is_free_for_summon(coord) {
+                            self.world
+                                .add_entity(coord, PackedEntity::summoned_bumper());
+                        }
+                        Ok(ApplyAction::Done)
+                    }
                 };
                 match result {
                     Ok(ApplyAction::Done) => (),
@@ -982,7 +2220,6 @@ impl Gws {
                 }
             }
         }
-        self.pathfinding.clear_commitments();
     }
     fn engine_commit(&mut self) {
         let player_coord = self.player().coord();
@@ -990,14 +2227,22 @@ impl Gws {
             .update_player_coord(player_coord, &self.world);
         for &id in self.world.npc_ids() {
             let npc = self.world.entities().get(&id).unwrap();
-            if npc.is_frozen() {
+            if npc.is_frozen() || npc.is_wading() {
                 continue;
             }
             if self
                 .world
                 .can_see(npc.coord(), player_coord, NPC_VISION_RANGE)
             {
-                if npc.foreground_tile() == Some(ForegroundTile::Caster)
+                if npc.is_boss() {
+                    commit_boss_action(
+                        &mut self.pathfinding,
+                        &self.world,
+                        id,
+                        player_coord,
+                        self.turns_taken,
+                    );
+                } else if npc.foreground_tile() == Some(ForegroundTile::Caster)
                     && (npc.coord().x == player_coord.x
                         || npc.coord().y == player_coord.y)
                     && npc.coord().manhattan_distance(player_coord) < 8
@@ -1078,10 +2323,27 @@ impl Gws {
                 }
             }
         }
+        // hasted npcs show their second action alongside the first
+        for &id in self.world.npc_ids() {
+            if self
+                .world
+                .entities()
+                .get(&id)
+                .unwrap()
+                .has_elite_trait(EliteTrait::Hasted)
+            {
+                self.pathfinding.commit_followup_action(id, &self.world);
+            }
+        }
         self.turn = Turn::Player;
     }
 
-    fn between_levels(&self) -> BetweenLevels {
+    fn between_levels(
+        &self,
+        next_level: u32,
+        route: Vec<Branch>,
+        arrival: Arrival,
+    ) -> BetweenLevels {
         let deck = self
             .deck()
             .iter()
@@ -1091,26 +2353,165 @@ impl Gws {
             .cloned()
             .collect::<Vec<_>>();
         let burnt = self.burnt.clone();
-        let player = self.world.pack_entity(self.player_id);
+        let mut player = self.world.pack_entity(self.player_id);
+        let mut world = self.world.clone();
+        world.remove_any_entity(self.player_id);
+        // rewards are only given the first time a level is left by the way down
+        let first_visit = arrival == Arrival::Descending && !self.rewards_paid;
+        let stored_levels = vec![StoredLevel {
+            level: self.level,
+            branch: self.branch(),
+            world,
+            visible_area: self.visible_area.clone(),
+            objective: self.objective,
+            turns_taken: self.turns_taken,
+            healing_used: self.healing_used,
+            boss_id: self.boss_id,
+            rewards_paid: self.rewards_paid || first_visit,
+            start_coord: self.start_coord,
+        }];
+        if first_visit && self.has_relic(Relic::Bandage) {
+            if let Some(hit_points) = player.hit_points.as_mut() {
+                hit_points.current = (hit_points.current + 1).min(hit_points.max);
+            }
+        }
         BetweenLevels {
             deck,
             burnt,
             player,
             hand_size: self.hand.len(),
             max_draw_countdown: self.draw_countdown.max,
-            next_level: self.level + 1,
+            next_level,
+            settings: self.settings.clone(),
+            relics: self.relics.clone(),
+            items: self.items.clone(),
+            gold: self.gold,
+            route,
+            stored_levels,
+            arrival,
+            reward_pending: first_visit,
+            bonus_upgrades: if !first_visit {
+                0
+            } else if self.objective_complete_on_exit() {
+                1 + self.world.elite_kills()
+            } else {
+                self.world.elite_kills()
+            },
+        }
+    }
+
+    /// Move the levels left earlier into the transition to the next level. Call this
+    /// once an `End::ExitLevel` is taken, as this game can no longer return to them.
+    pub fn take_stored_levels(&mut self, between_levels: &mut BetweenLevels) {
+        let mut stored_levels = mem::take(&mut self.stored_levels);
+        stored_levels.append(&mut between_levels.stored_levels);
+        between_levels.stored_levels = stored_levels;
+    }
+
+    /// Each elite killed on this level grants a bonus upgrade.
+    pub fn elite_kills(&self) -> u32 {
+        self.world.elite_kills()
+    }
+
+    pub fn objective(&self) -> Option<Objective> {
+        self.objective
+    }
+
+    pub fn turns_taken(&self) -> u32 {
+        self.turns_taken
+    }
+
+    pub fn objective_status(&self) -> Option<ObjectiveStatus> {
+        use ObjectiveStatus::*;
+        self.objective.map(|objective| match objective {
+            Objective::ClearEnemies => {
+                if self.world.npc_ids().next().is_none() {
+                    Complete
+                } else {
+                    InProgress
+                }
+            }
+            Objective::ReachStairsWithin { turns } => {
+                if self.turns_taken > turns {
+                    Failed
+                } else {
+                    InProgress
+                }
+            }
+            Objective::CollectKey => {
+                let key_remaining = self
+                    .world
+                    .entities()
+                    .values()
+                    .any(|e| e.foreground_tile() == Some(ForegroundTile::Key));
+                if key_remaining {
+                    InProgress
+                } else {
+                    Complete
+                }
+            }
+            Objective::NoHealing => {
+                if self.healing_used {
+                    Failed
+                } else {
+                    InProgress
+                }
+            }
+        })
+    }
+
+    fn objective_complete_on_exit(&self) -> bool {
+        match (self.objective, self.objective_status()) {
+            (Some(objective), Some(ObjectiveStatus::InProgress)) => {
+                objective.complete_on_exit()
+            }
+            (_, status) => status == Some(ObjectiveStatus::Complete),
         }
     }
 
+    /// True while the final level's boss is still alive.
+    pub fn is_end_guarded(&self) -> bool {
+        self.boss().is_some()
+    }
+
+    pub fn boss(&self) -> Option<&Entity> {
+        self.boss_id.and_then(|id| self.world.entities().get(&id))
+    }
+
     fn check_end(&self) -> Option<End> {
         let player = self.player();
+        // in endless mode killing the boss only opens the way to the end
+        if self.settings.mode == GameMode::Standard
+            && self.boss_id.is_some()
+            && self.boss().is_none()
+        {
+            return Some(End::Victory);
+        }
         if let Some(cell) = self.world.grid().get(player.coord()) {
-            if cell.is_end() {
+            if cell.is_end() && !self.is_end_guarded() {
                 return Some(End::Victory);
             }
             for entity in cell.entity_iter(self.world.entities()) {
-                if entity.foreground_tile() == Some(ForegroundTile::Stairs) {
-                    return Some(End::ExitLevel(self.between_levels()));
+                match entity.foreground_tile() {
+                    Some(ForegroundTile::Stairs) => {
+                        let branch = entity.branch().unwrap_or(Branch::Main);
+                        let route =
+                            self.route.iter().cloned().chain(Some(branch)).collect();
+                        return Some(End::ExitLevel(Box::new(self.between_levels(
+                            self.level + 1,
+                            route,
+                            Arrival::Descending,
+                        ))));
+                    }
+                    Some(ForegroundTile::UpStairs) => {
+                        let route = self.route[..self.route.len() - 1].to_vec();
+                        return Some(End::ExitLevel(Box::new(self.between_levels(
+                            self.level - 1,
+                            route,
+                            Arrival::Ascending(self.branch()),
+                        ))));
+                    }
+                    _ => (),
                 }
             }
         }
@@ -1157,6 +2558,9 @@ impl Gws {
                                 ForegroundTile::Flame => InteractiveType::Flame,
                                 ForegroundTile::Altar => InteractiveType::Altar,
                                 ForegroundTile::Fountain => InteractiveType::Fountain,
+                                ForegroundTile::Anvil => InteractiveType::Anvil,
+                                ForegroundTile::Chest => InteractiveType::Chest,
+                                ForegroundTile::Merchant => InteractiveType::Merchant,
                                 _ => panic!("illegal interactive"),
                             };
                             return Some(Tick::Interact(Interactive { typ, entity_id }));
@@ -1173,6 +2577,8 @@ impl Gws {
         }
         if self.animation.is_empty() {
             if self.turn == Turn::Engine {
+                self.pick_up_gold();
+                self.pick_up_item();
                 self.engine_turn();
             }
             if self.animation.is_empty() {
@@ -1188,6 +2594,10 @@ impl Gws {
         self.world.entities().get(&self.player_id).unwrap()
     }
 
+    fn player_cell(&self) -> &WorldCell {
+        self.world.grid().get_checked(self.player().coord())
+    }
+
     fn update_visible_area(&mut self) {
         self.visible_area.update(self.player().coord(), &self.world);
     }
@@ -1219,20 +2629,79 @@ impl Gws {
     pub fn burnt(&self) -> &[Card] {
         &self.burnt
     }
+    pub fn gold(&self) -> u32 {
+        self.gold
+    }
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+    /// The branch taken on each level so far, ending with the current level's.
+    pub fn route(&self) -> &[Branch] {
+        &self.route
+    }
+    fn branch(&self) -> Branch {
+        *self.route.last().unwrap()
+    }
+    /// The branches reached by stairs discovered on this level.
+    pub fn discovered_exits(&self) -> Vec<Branch> {
+        let mut exits = Vec::new();
+        for entity in self.world.entities().values() {
+            if entity.foreground_tile() == Some(ForegroundTile::Stairs)
+                && self.visible_area.is_discovered(entity.coord())
+            {
+                let branch = entity.branch().unwrap_or(Branch::Main);
+                if !exits.contains(&branch) {
+                    exits.push(branch);
+                }
+            }
+        }
+        exits
+    }
+    /// The number of times the deck has been reshuffled on this level. Under the
+    /// fatigue rule this is also the damage dealt by the latest reshuffle.
+    pub fn reshuffles(&self) -> u32 {
+        self.reshuffles
+    }
+    pub fn items_left_behind(&self) -> u32 {
+        self.items_left_behind
+    }
+    pub fn can_mulligan(&self) -> bool {
+        !self.mulligan_used_this_hand
+    }
+    pub fn reshuffle_rule(&self) -> ReshuffleRule {
+        self.settings.reshuffle
+    }
+    pub fn relics(&self) -> &[Relic] {
+        &self.relics
+    }
+    pub fn has_relic(&self, relic: Relic) -> bool {
+        self.relics.contains(&relic)
+    }
+    /// The power it would take to play a card from the hand right now.
+    pub fn card_cost(&self, card: Card) -> u32 {
+        if self.has_relic(Relic::Hourglass) && !self.card_played_this_hand {
+            0
+        } else {
+            card.cost()
+        }
+    }
+    fn blink_bonus(&self) -> u32 {
+        if self.has_relic(Relic::Lodestone) {
+            LODESTONE_BLINK_BONUS
+        } else {
+            0
+        }
+    }
+    /// False once the hand has as many slots as can be shown.
+    pub fn can_upgrade_hand(&self) -> bool {
+        self.hand.len() < MAX_NUM_CARDS
+    }
     pub fn choose_upgrades<R: Rng>(
         &self,
         amount: usize,
         rng: &mut R,
     ) -> impl Iterator<Item = &'static CharacterUpgrade> {
-        use CharacterUpgrade::*;
-        const WITH_HAND: &'static [CharacterUpgrade] = &[Life, Power, Hand, Vision];
-        const WITHOUT_HAND: &'static [CharacterUpgrade] = &[Life, Power, Vision];
-        let slice = if self.hand.len() >= MAX_NUM_CARDS {
-            WITHOUT_HAND
-        } else {
-            WITH_HAND
-        };
-        slice.choose_multiple(rng, amount)
+        choose_character_upgrades(self.hand.len(), amount, rng)
     }
     pub fn choose_negative_cards<R: Rng>(
         &self,
@@ -1251,4 +2720,10 @@ impl Gws {
     pub fn dungeon_level(&self) -> u32 {
         self.level + 1
     }
+    pub fn mode(&self) -> GameMode {
+        self.settings.mode
+    }
+    pub fn settings(&self) -> &RunSettings {
+        &self.settings
+    }
 }
@@ -1,3 +1,4 @@
+use crate::vision::*;
 use crate::world::*;
 use direction::*;
 use grid_2d::*;
@@ -8,6 +9,8 @@ pub enum CommitmentType {
     Move,
     Cast,
     Heal(u32),
+    Slam,
+    Summon,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -75,21 +78,39 @@ pub struct PathfindingContext {
     search: SearchContext<u32>,
     bfs: BfsContext,
     distance_to_player: UniformDistanceMap<u32, DirectionsCardinal>,
+    /// Distances for npcs which can break through cracked walls.
+    distance_to_player_breaking_walls: UniformDistanceMap<u32, DirectionsCardinal>,
     path: Vec<Direction>,
     commitment_grid: CommitmentGrid,
     player_coord: Coord,
     committed_actions: Vec<(EntityId, CardinalDirection, CommitmentType)>,
+    /// Second actions of hasted npcs, taken after all the committed actions, along with
+    /// where the npc must be for its second action to still make sense.
+    followup_actions: Vec<(EntityId, Coord, CardinalDirection, CommitmentType)>,
 }
 
-struct Solid<'a>(&'a World);
+struct Solid<'a> {
+    world: &'a World,
+    breaks_walls: bool,
+}
 struct SolidOrOccupied<'a> {
     world: &'a World,
     commitment_grid: &'a CommitmentGrid,
+    breaks_walls: bool,
+}
+
+// npcs open doors by walking into them, and some can break through cracked walls
+fn blocks_npc(cell: &WorldCell, breaks_walls: bool) -> bool {
+    let passable = cell.is_closed_door() || (breaks_walls && cell.is_cracked_wall());
+    (cell.is_solid() && !passable) || cell.is_hazardous()
 }
 
 impl<'a> SolidGrid for Solid<'a> {
     fn is_solid(&self, coord: Coord) -> Option<bool> {
-        self.0.grid().get(coord).map(|cell| cell.is_solid())
+        self.world
+            .grid()
+            .get(coord)
+            .map(|cell| blocks_npc(cell, self.breaks_walls))
     }
 }
 
@@ -97,7 +118,7 @@ impl<'a> SolidGrid for SolidOrOccupied<'a> {
     fn is_solid(&self, coord: Coord) -> Option<bool> {
         if let Some(cell) = self.world.grid().get(coord) {
             Some(
-                cell.is_solid()
+                blocks_npc(cell, self.breaks_walls)
                     || cell.contains_npc()
                     || self.commitment_grid.is_committed(coord),
             )
@@ -107,6 +128,47 @@ impl<'a> SolidGrid for SolidOrOccupied<'a> {
     }
 }
 
+// the player only travels through cells they have seen, and avoids anything harmful
+struct Travel<'a> {
+    world: &'a World,
+    visible_area: &'a VisibileArea,
+    destination: Option<Coord>,
+}
+
+impl<'a> SolidGrid for Travel<'a> {
+    fn is_solid(&self, coord: Coord) -> Option<bool> {
+        self.world.grid().get(coord).map(|cell| {
+            !self.visible_area.is_discovered(coord)
+                || (cell.is_solid() && !cell.is_closed_door())
+                || cell.is_hazardous()
+                || cell.contains_npc()
+                || cell.contains_spike()
+                || (Some(coord) != self.destination && self.contains_stairs(cell))
+        })
+    }
+}
+
+impl<'a> Travel<'a> {
+    // stairs are only ever taken on purpose
+    fn contains_stairs(&self, cell: &WorldCell) -> bool {
+        cell.entity_iter(self.world.entities()).any(|entity| {
+            matches!(
+                entity.foreground_tile(),
+                Some(ForegroundTile::Stairs) | Some(ForegroundTile::UpStairs)
+            )
+        })
+    }
+    // a cell the player can stand on which borders the unexplored part of the map
+    fn is_frontier(&self, coord: Coord) -> bool {
+        self.is_solid(coord) == Some(false)
+            && Directions.into_iter().any(|direction| {
+                let neighbour = coord + direction.coord();
+                neighbour.is_valid(self.world.grid().size())
+                    && !self.visible_area.is_discovered(neighbour)
+            })
+    }
+}
+
 const CONFIG: SearchConfig = SearchConfig {
     allow_solid_start: true,
 };
@@ -121,7 +183,12 @@ impl PathfindingContext {
             bfs: BfsContext::new(size),
             commitment_grid: CommitmentGrid::new(size),
             distance_to_player: UniformDistanceMap::new(size, DirectionsCardinal),
+            distance_to_player_breaking_walls: UniformDistanceMap::new(
+                size,
+                DirectionsCardinal,
+            ),
             committed_actions: Vec::new(),
+            followup_actions: Vec::new(),
             path: Vec::new(),
         }
     }
@@ -130,18 +197,27 @@ impl PathfindingContext {
     }
     pub fn update_player_coord(&mut self, player_coord: Coord, world: &World) {
         if player_coord.is_valid(world.grid().size()) {
-            self.bfs
-                .populate_uniform_distance_map(
-                    &Solid(world),
-                    player_coord,
-                    Default::default(),
-                    &mut self.distance_to_player,
-                )
-                .expect("Failed to update distance to player");
+            for (breaks_walls, distance_map) in [
+                (false, &mut self.distance_to_player),
+                (true, &mut self.distance_to_player_breaking_walls),
+            ] {
+                self.bfs
+                    .populate_uniform_distance_map(
+                        &Solid {
+                            world,
+                            breaks_walls,
+                        },
+                        player_coord,
+                        Default::default(),
+                        distance_map,
+                    )
+                    .expect("Failed to update distance to player");
+            }
         }
         self.player_coord = player_coord;
         self.commitment_grid.clear();
         self.committed_actions.clear();
+        self.followup_actions.clear();
     }
     pub fn clear_commitments(&mut self) {
         self.commitment_grid.clear();
@@ -149,17 +225,23 @@ impl PathfindingContext {
     pub fn direction_towards_player(
         &mut self,
         coord: Coord,
+        breaks_walls: bool,
         world: &World,
     ) -> Option<CardinalDirection> {
         let result = self.search.best_search_uniform_distance_map(
             &SolidOrOccupied {
                 world,
                 commitment_grid: &self.commitment_grid,
+                breaks_walls,
             },
             coord,
             CONFIG,
             MAX_DEPTH,
-            &self.distance_to_player,
+            if breaks_walls {
+                &self.distance_to_player_breaking_walls
+            } else {
+                &self.distance_to_player
+            },
             &mut self.path,
         );
         match result {
@@ -168,8 +250,11 @@ impl PathfindingContext {
         }
     }
     pub fn commit_action(&mut self, id: EntityId, world: &World, typ: CommitmentType) {
-        let coord = world.entities().get(&id).unwrap().coord();
-        if let Some(direction) = self.direction_towards_player(coord, world) {
+        let entity = world.entities().get(&id).unwrap();
+        let coord = entity.coord();
+        if let Some(direction) =
+            self.direction_towards_player(coord, entity.breaks_walls(), world)
+        {
             let next_coord = coord + direction.coord();
             if next_coord != self.player_coord {
                 self.commitment_grid.commit(next_coord, direction, typ);
@@ -177,7 +262,144 @@ impl PathfindingContext {
             self.committed_actions.push((id, direction, typ));
         }
     }
+    /// Commit to a second action of the same kind, taken from where the first leaves the npc.
+    pub fn commit_followup_action(&mut self, id: EntityId, world: &World) {
+        let (direction, typ) = match self.committed_action(id) {
+            Some(action) => action,
+            None => return,
+        };
+        let entity = world.entities().get(&id).unwrap();
+        let followup = match typ {
+            CommitmentType::Move => {
+                let mut coord = entity.coord() + direction.coord();
+                if coord == self.player_coord {
+                    coord = entity.coord();
+                }
+                self.direction_towards_player(coord, entity.breaks_walls(), world)
+                    .map(|direction| (coord, direction, CommitmentType::Move))
+            }
+            CommitmentType::Cast => Some((entity.coord(), direction, typ)),
+            CommitmentType::Heal(count) if count > 0 => {
+                Some((entity.coord(), direction, CommitmentType::Heal(count - 1)))
+            }
+            CommitmentType::Heal(_) | CommitmentType::Slam | CommitmentType::Summon => {
+                None
+            }
+        };
+        if let Some((coord, direction, typ)) = followup {
+            let next_coord = coord + direction.coord();
+            if next_coord != self.player_coord && next_coord.is_valid(world.grid().size())
+            {
+                self.commitment_grid.commit(next_coord, direction, typ);
+            }
+            self.followup_actions.push((id, coord, direction, typ));
+        }
+    }
+    pub fn travel_direction(
+        &mut self,
+        world: &World,
+        visible_area: &VisibileArea,
+        start: Coord,
+        goal: Coord,
+    ) -> Option<CardinalDirection> {
+        let result = self.bfs.bfs(
+            &Travel {
+                world,
+                visible_area,
+                destination: Some(goal),
+            },
+            start,
+            goal,
+            DirectionsCardinal,
+            CONFIG,
+            &mut self.path,
+        );
+        match result {
+            Ok(_) => self.path.first().and_then(|d| d.cardinal()),
+            Err(_) => None,
+        }
+    }
+    pub fn commit_action_in_direction(
+        &mut self,
+        id: EntityId,
+        coord: Coord,
+        direction: CardinalDirection,
+        typ: CommitmentType,
+    ) {
+        self.commitment_grid
+            .commit(coord + direction.coord(), direction, typ);
+        self.committed_actions.push((id, direction, typ));
+    }
+    /// Commit to an action which affects every cell in an area.
+    pub fn commit_area<I: IntoIterator<Item = (Coord, CardinalDirection)>>(
+        &mut self,
+        id: EntityId,
+        direction: CardinalDirection,
+        typ: CommitmentType,
+        cells: I,
+    ) {
+        for (coord, cell_direction) in cells {
+            self.commitment_grid.commit(coord, cell_direction, typ);
+        }
+        self.committed_actions.push((id, direction, typ));
+    }
+    pub fn explore_direction(
+        &mut self,
+        world: &World,
+        visible_area: &VisibileArea,
+        start: Coord,
+    ) -> Option<CardinalDirection> {
+        let travel = Travel {
+            world,
+            visible_area,
+            destination: None,
+        };
+        let result = self.bfs.bfs_predicate(
+            &travel,
+            start,
+            |coord| coord != start && travel.is_frontier(coord),
+            DirectionsCardinal,
+            CONFIG,
+            &mut self.path,
+        );
+        match result {
+            Ok(_) => self.path.first().and_then(|d| d.cardinal()),
+            Err(_) => None,
+        }
+    }
+    pub fn committed_action(
+        &self,
+        id: EntityId,
+    ) -> Option<(CardinalDirection, CommitmentType)> {
+        self.committed_actions
+            .iter()
+            .find(|&&(committed_id, _, _)| committed_id == id)
+            .map(|&(_, direction, typ)| (direction, typ))
+    }
     pub fn committed_actions(&self) -> &[(EntityId, CardinalDirection, CommitmentType)] {
         &self.committed_actions
     }
+    pub fn followup_action(
+        &self,
+        id: EntityId,
+    ) -> Option<(CardinalDirection, CommitmentType)> {
+        self.followup_actions
+            .iter()
+            .find(|&&(followup_id, _, _, _)| followup_id == id)
+            .map(|&(_, _, direction, typ)| (direction, typ))
+    }
+    pub fn followup_actions(
+        &self,
+    ) -> impl '_ + Iterator<Item = (EntityId, CardinalDirection, CommitmentType)> {
+        self.followup_actions
+            .iter()
+            .map(|&(id, _, direction, typ)| (id, direction, typ))
+    }
+    /// Forget the second actions of npcs whose first action left them somewhere other
+    /// than planned, e.g. because their way was blocked.
+    pub fn drop_stale_followups(&mut self, world: &World) {
+        self.followup_actions.retain(|&(id, coord, _, _)| {
+            world.entities().get(&id).map(|entity| entity.coord()) == Some(coord)
+        });
+    }
 }
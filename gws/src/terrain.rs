@@ -13,6 +13,7 @@ pub struct TerrainDescription {
     pub player_coord: Coord,
     pub size: Size,
     pub instructions: Vec<Instruction>,
+    pub objective: Option<Objective>,
 }
 
 impl TerrainDescription {
@@ -21,8 +22,12 @@ impl TerrainDescription {
             player_coord,
             size,
             instructions,
+            objective: None,
         }
     }
+    fn with_objective(self, objective: Option<Objective>) -> Self {
+        Self { objective, ..self }
+    }
 }
 
 fn string_to_char_grid(s: &str) -> Grid<char> {
@@ -36,13 +41,391 @@ fn string_to_char_grid(s: &str) -> Grid<char> {
 }
 
 #[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
-enum Base {
+pub enum Base {
     Floor,
     Ground,
     Tree,
     IceWall,
     BrickWall,
     StoneWall,
+    Water,
+    Lava,
+    Chasm,
+    Door,
+    CrackedWall,
+}
+
+impl Base {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "floor" => Some(Base::Floor),
+            "ground" => Some(Base::Ground),
+            "tree" => Some(Base::Tree),
+            "ice-wall" => Some(Base::IceWall),
+            "brick-wall" => Some(Base::BrickWall),
+            "stone-wall" => Some(Base::StoneWall),
+            "water" => Some(Base::Water),
+            "lava" => Some(Base::Lava),
+            "chasm" => Some(Base::Chasm),
+            "door" => Some(Base::Door),
+            "cracked-wall" => Some(Base::CrackedWall),
+            _ => None,
+        }
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            Base::Floor => "floor",
+            Base::Ground => "ground",
+            Base::Tree => "tree",
+            Base::IceWall => "ice-wall",
+            Base::BrickWall => "brick-wall",
+            Base::StoneWall => "stone-wall",
+            Base::Water => "water",
+            Base::Lava => "lava",
+            Base::Chasm => "chasm",
+            Base::Door => "door",
+            Base::CrackedWall => "cracked-wall",
+        }
+    }
+    pub fn is_wall(self) -> bool {
+        match self {
+            Base::IceWall
+            | Base::BrickWall
+            | Base::StoneWall
+            | Base::Tree
+            | Base::CrackedWall => true,
+            Base::Floor
+            | Base::Ground
+            | Base::Water
+            | Base::Lava
+            | Base::Chasm
+            | Base::Door => false,
+        }
+    }
+    /// Cells which the level generator treats as connecting areas. Trees, water and
+    /// doors can be walked through, but nothing should be placed on them.
+    fn is_passable(self) -> bool {
+        match self {
+            Base::Floor | Base::Ground | Base::Tree | Base::Water | Base::Door => true,
+            Base::IceWall
+            | Base::BrickWall
+            | Base::StoneWall
+            | Base::Lava
+            | Base::Chasm
+            | Base::CrackedWall => false,
+        }
+    }
+    fn is_open(self) -> bool {
+        match self {
+            Base::Floor | Base::Ground => true,
+            Base::IceWall
+            | Base::BrickWall
+            | Base::StoneWall
+            | Base::Tree
+            | Base::Water
+            | Base::Lava
+            | Base::Chasm
+            | Base::Door
+            | Base::CrackedWall => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PaletteEntry {
+    pub ch: char,
+    pub colour: Rgb24,
+    pub base: Base,
+}
+
+/// Maps the characters of a wfc sample to the colours used to draw it in an image, and
+/// to the terrain they become once the level is generated.
+#[derive(Debug, Clone)]
+pub struct Palette {
+    entries: Vec<PaletteEntry>,
+}
+
+#[derive(Debug)]
+pub enum PaletteError {
+    Syntax { line: usize },
+    InvalidColour { line: usize, colour: String },
+    UnknownBase { line: usize, name: String },
+    DuplicateChar { line: usize, ch: char },
+    DuplicateColour { line: usize, colour: String },
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        let entry = |ch, colour, base| PaletteEntry { ch, colour, base };
+        Self {
+            entries: vec![
+                entry('#', rgb24(0, 0, 0), Base::IceWall),
+                entry('.', rgb24(255, 255, 255), Base::Floor),
+                entry('$', rgb24(0, 0, 255), Base::IceWall),
+                entry('?', rgb24(255, 0, 0), Base::IceWall),
+                entry(',', rgb24(128, 128, 128), Base::Ground),
+                entry('&', rgb24(0, 255, 0), Base::Tree),
+                entry('%', rgb24(0, 128, 0), Base::Tree),
+                entry('~', rgb24(0, 255, 255), Base::Water),
+                entry('=', rgb24(255, 128, 0), Base::Lava),
+                entry(':', rgb24(64, 64, 64), Base::Chasm),
+                entry('|', rgb24(160, 80, 0), Base::Door),
+                entry('*', rgb24(96, 96, 96), Base::CrackedWall),
+            ],
+        }
+    }
+}
+
+impl Palette {
+    /// Parses a palette with one entry per line, of the form "<char> <rrggbb> <base>",
+    /// e.g. "# 000000 ice-wall". Blank lines are ignored.
+    pub fn parse(s: &str) -> Result<Self, PaletteError> {
+        let mut entries: Vec<PaletteEntry> = Vec::new();
+        for (index, line) in s.lines().enumerate() {
+            let line_number = index + 1;
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.is_empty() {
+                continue;
+            }
+            let (ch, colour_str, name) = match fields.as_slice() {
+                &[ch, colour, name] if ch.chars().count() == 1 => {
+                    (ch.chars().next().unwrap(), colour, name)
+                }
+                _ => return Err(PaletteError::Syntax { line: line_number }),
+            };
+            let colour = parse_hex_colour(colour_str).ok_or_else(|| {
+                PaletteError::InvalidColour {
+                    line: line_number,
+                    colour: colour_str.to_string(),
+                }
+            })?;
+            let base =
+                Base::from_name(name).ok_or_else(|| PaletteError::UnknownBase {
+                    line: line_number,
+                    name: name.to_string(),
+                })?;
+            if entries.iter().any(|e| e.ch == ch) {
+                return Err(PaletteError::DuplicateChar {
+                    line: line_number,
+                    ch,
+                });
+            }
+            // images are read back by colour, so each colour must name one character
+            if entries.iter().any(|e| e.colour == colour) {
+                return Err(PaletteError::DuplicateColour {
+                    line: line_number,
+                    colour: colour_str.to_string(),
+                });
+            }
+            entries.push(PaletteEntry { ch, colour, base });
+        }
+        Ok(Self { entries })
+    }
+    pub fn entries(&self) -> &[PaletteEntry] {
+        &self.entries
+    }
+    pub fn base_of_char(&self, ch: char) -> Option<Base> {
+        self.entries.iter().find(|e| e.ch == ch).map(|e| e.base)
+    }
+    pub fn char_of_colour(&self, colour: Rgb24) -> Option<char> {
+        self.entries
+            .iter()
+            .find(|e| e.colour == colour)
+            .map(|e| e.ch)
+    }
+    pub fn colour_of_char(&self, ch: char) -> Option<Rgb24> {
+        self.entries.iter().find(|e| e.ch == ch).map(|e| e.colour)
+    }
+}
+
+fn parse_hex_colour(s: &str) -> Option<Rgb24> {
+    let s = s.trim_start_matches("0x");
+    if s.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(s.get(i..i + 2)?, 16).ok();
+    Some(rgb24(channel(0)?, channel(2)?, channel(4)?))
+}
+
+const WFC_PATTERN_SIZE: u32 = 4;
+
+/// Top-left corners of patterns in the builtin samples which trace the outer wall. These
+/// patterns are forbidden away from the edges of the generated level.
+const WFC_EDGE_ANCHORS: &[Coord] = &[
+    Coord::new(20, 0),
+    Coord::new(5, 26),
+    Coord::new(22, 54),
+    Coord::new(41, 53),
+    Coord::new(33, 56),
+];
+
+/// Top-left corner of a pattern in the builtin samples which is entirely floor. This
+/// pattern is never chosen, to avoid large featureless areas.
+const WFC_OPEN_FLOOR_ANCHOR: Coord = Coord::new(8, 8);
+
+/// Reasons a sample may generate poor levels, or fail to generate levels at all.
+#[derive(Debug)]
+pub enum SampleProblem {
+    NoFloor,
+    NoWall,
+    /// The bottom row and right column must consist of a single marker character
+    /// which is used to pin the bottom-right corner of the generated level.
+    MissingBorderMarker,
+    BorderMarkerInInterior(Coord),
+    AnchorOutOfBounds(Coord),
+    EdgeAnchorWithoutWall(Coord),
+    OpenFloorAnchorNotFloor(Coord),
+}
+
+impl SampleProblem {
+    /// Whether the sample can never generate a level, rather than generating poor ones.
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            SampleProblem::NoFloor
+            | SampleProblem::MissingBorderMarker
+            | SampleProblem::BorderMarkerInInterior(_) => true,
+            SampleProblem::NoWall
+            | SampleProblem::AnchorOutOfBounds(_)
+            | SampleProblem::EdgeAnchorWithoutWall(_)
+            | SampleProblem::OpenFloorAnchorNotFloor(_) => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SampleError {
+    Empty,
+    RaggedRow { y: u32 },
+    TooSmall(Size),
+    UnknownChars(Vec<(Coord, char)>),
+    UnknownColours(Vec<(Coord, Rgb24)>),
+}
+
+/// An input to wave function collapse, along with the palette used to interpret it.
+#[derive(Debug, Clone)]
+pub struct WfcSample {
+    chars: Grid<char>,
+    palette: Palette,
+}
+
+impl WfcSample {
+    fn new(chars: Grid<char>, palette: Palette) -> Result<Self, SampleError> {
+        let size = chars.size();
+        if size.width() < WFC_PATTERN_SIZE || size.height() < WFC_PATTERN_SIZE {
+            return Err(SampleError::TooSmall(size));
+        }
+        let unknown = chars
+            .enumerate()
+            .filter(|&(_, &ch)| palette.base_of_char(ch).is_none())
+            .map(|(coord, &ch)| (coord, ch))
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            return Err(SampleError::UnknownChars(unknown));
+        }
+        Ok(Self { chars, palette })
+    }
+    pub fn from_text(s: &str, palette: Palette) -> Result<Self, SampleError> {
+        let rows = s
+            .lines()
+            .filter(|s| !s.is_empty())
+            .map(|s| s.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let width = rows.first().ok_or(SampleError::Empty)?.len();
+        if let Some(y) = rows.iter().position(|row| row.len() != width) {
+            return Err(SampleError::RaggedRow { y: y as u32 });
+        }
+        let size = Size::new(width as u32, rows.len() as u32);
+        let chars = Grid::new_fn(size, |Coord { x, y }| rows[y as usize][x as usize]);
+        Self::new(chars, palette)
+    }
+    /// Builds a sample from an image, where `get_pixel` returns the colour at a coord.
+    pub fn from_pixels<F>(
+        size: Size,
+        get_pixel: F,
+        palette: Palette,
+    ) -> Result<Self, SampleError>
+    where
+        F: FnMut(Coord) -> Rgb24,
+    {
+        let colours = Grid::new_fn(size, get_pixel);
+        let unknown = colours
+            .enumerate()
+            .filter(|&(_, &colour)| palette.char_of_colour(colour).is_none())
+            .map(|(coord, &colour)| (coord, colour))
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            return Err(SampleError::UnknownColours(unknown));
+        }
+        let chars =
+            Grid::new_grid_map(colours, |colour| palette.char_of_colour(colour).unwrap());
+        Self::new(chars, palette)
+    }
+    pub fn chars(&self) -> &Grid<char> {
+        &self.chars
+    }
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+    /// Checks that the sample has the features that wave function collapse relies on
+    /// to generate levels.
+    pub fn validate(&self) -> Vec<SampleProblem> {
+        let mut problems = Vec::new();
+        let base_grid = char_grid_to_base_grid(&self.chars, &self.palette);
+        if base_grid.iter().all(|base| base.is_wall()) {
+            problems.push(SampleProblem::NoFloor);
+        }
+        if !base_grid.iter().any(|base| base.is_wall()) {
+            problems.push(SampleProblem::NoWall);
+        }
+        let right = self.chars.width() as i32 - 1;
+        let bottom = self.chars.height() as i32 - 1;
+        let marker = *self.chars.get_checked(Coord::new(right, bottom));
+        let is_border = |coord: Coord| coord.x == right || coord.y == bottom;
+        if self
+            .chars
+            .enumerate()
+            .any(|(coord, &ch)| is_border(coord) && ch != marker)
+        {
+            problems.push(SampleProblem::MissingBorderMarker);
+        } else {
+            for (coord, &ch) in self.chars.enumerate() {
+                if !is_border(coord) && ch == marker {
+                    problems.push(SampleProblem::BorderMarkerInInterior(coord));
+                }
+            }
+        }
+        let anchor_pattern = |anchor: Coord| {
+            XThenYIter::new(Size::new(WFC_PATTERN_SIZE, WFC_PATTERN_SIZE))
+                .map(|offset| base_grid.get(anchor + offset).cloned())
+                .collect::<Option<Vec<_>>>()
+        };
+        for &anchor in WFC_EDGE_ANCHORS {
+            match anchor_pattern(anchor) {
+                None => problems.push(SampleProblem::AnchorOutOfBounds(anchor)),
+                Some(pattern) => {
+                    if !pattern.iter().any(|base| base.is_wall()) {
+                        problems.push(SampleProblem::EdgeAnchorWithoutWall(anchor));
+                    }
+                }
+            }
+        }
+        match anchor_pattern(WFC_OPEN_FLOOR_ANCHOR) {
+            None => {
+                problems.push(SampleProblem::AnchorOutOfBounds(WFC_OPEN_FLOOR_ANCHOR))
+            }
+            Some(pattern) => {
+                if pattern.iter().any(|base| base.is_wall()) {
+                    problems.push(SampleProblem::OpenFloorAnchorNotFloor(
+                        WFC_OPEN_FLOOR_ANCHOR,
+                    ));
+                }
+            }
+        }
+        problems
+    }
+    fn builtin(s: &str) -> Self {
+        Self::from_text(s, Palette::default()).expect("invalid builtin sample")
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -53,12 +436,21 @@ enum Contents {
     Bruiser,
     Caster,
     Healer,
+    HeavyBruiser,
+    HeavyCaster,
+    Boss,
     Light(Rgb24),
-    Stairs,
+    Stairs(Branch),
+    UpStairs,
     Flame,
     Altar,
     Fountain,
+    Anvil,
+    Chest,
+    Merchant,
+    Item(Item),
     NaturalSpike,
+    Key,
 }
 
 #[derive(Debug, Clone)]
@@ -66,6 +458,7 @@ struct Cell {
     base: Base,
     contents: Option<Contents>,
     upgrade: Option<Upgrade>,
+    elite_traits: Vec<EliteTrait>,
 }
 
 impl Cell {
@@ -74,6 +467,7 @@ impl Cell {
             base,
             contents: None,
             upgrade: None,
+            elite_traits: Vec::new(),
         }
     }
     fn with_contents(self, contents: Contents) -> Self {
@@ -109,9 +503,29 @@ fn cell_grid_to_terrain_description<R: Rng>(
                 Contents::Flame => {
                     instructions.push(AddEntity(coord, PackedEntity::flame()));
                 }
+                Contents::Anvil => {
+                    instructions.push(AddEntity(coord, PackedEntity::anvil()));
+                }
+                Contents::Chest => {
+                    instructions.push(AddEntity(coord, PackedEntity::chest()));
+                }
+                Contents::Merchant => {
+                    if let Some(upgrade) = cell.upgrade.as_ref() {
+                        instructions.push(AddEntity(
+                            coord,
+                            PackedEntity::merchant(upgrade.clone()),
+                        ));
+                    }
+                }
+                Contents::Item(item) => {
+                    instructions.push(AddEntity(coord, PackedEntity::item(*item)));
+                }
                 Contents::HealthPickup => {
                     instructions.push(AddEntity(coord, PackedEntity::health_pickup()));
                 }
+                Contents::Key => {
+                    instructions.push(AddEntity(coord, PackedEntity::key()));
+                }
                 Contents::Altar => {
                     if let Some(upgrade) = cell.upgrade.as_ref() {
                         instructions
@@ -130,23 +544,68 @@ fn cell_grid_to_terrain_description<R: Rng>(
                     player_coord = Some(coord);
                 }
                 Contents::Bruiser => {
-                    instructions.push(AddEntity(coord, PackedEntity::bumper()));
+                    instructions.push(AddEntity(
+                        coord,
+                        PackedEntity::bumper()
+                            .with_elite_traits(cell.elite_traits.clone()),
+                    ));
                 }
                 Contents::Caster => {
-                    instructions.push(AddEntity(coord, PackedEntity::caster()));
+                    instructions.push(AddEntity(
+                        coord,
+                        PackedEntity::caster()
+                            .with_elite_traits(cell.elite_traits.clone()),
+                    ));
                 }
                 Contents::Healer => {
-                    instructions.push(AddEntity(coord, PackedEntity::healer()));
+                    instructions.push(AddEntity(
+                        coord,
+                        PackedEntity::healer()
+                            .with_elite_traits(cell.elite_traits.clone()),
+                    ));
+                }
+                Contents::HeavyBruiser => {
+                    instructions.push(AddEntity(
+                        coord,
+                        PackedEntity::heavy_bumper()
+                            .with_elite_traits(cell.elite_traits.clone()),
+                    ));
+                }
+                Contents::HeavyCaster => {
+                    instructions.push(AddEntity(
+                        coord,
+                        PackedEntity::heavy_caster()
+                            .with_elite_traits(cell.elite_traits.clone()),
+                    ));
                 }
                 Contents::End => {
                     instructions.push(AddEntity(coord, PackedEntity::end()));
                 }
-                Contents::Stairs => {
+                Contents::Boss => {
+                    instructions.push(AddEntity(coord, PackedEntity::boss()));
+                }
+                Contents::Stairs(branch) => {
+                    let light_colour = match branch {
+                        Branch::Main => Rgb24::new(128, 0, 0),
+                        Branch::Ice => Rgb24::new(0, 64, 128),
+                        Branch::Ruins => Rgb24::new(96, 0, 128),
+                    };
                     instructions.push(AddEntity(
                         coord,
                         PackedEntity {
                             foreground_tile: Some(ForegroundTile::Stairs),
-                            light: Some(basic_light(Rgb24::new(128, 0, 0))),
+                            light: Some(basic_light(light_colour)),
+                            branch: Some(*branch),
+                            ..Default::default()
+                        },
+                    ));
+                }
+                Contents::UpStairs => {
+                    instructions.push(AddEntity(
+                        coord,
+                        PackedEntity {
+                            foreground_tile: Some(ForegroundTile::UpStairs),
+                            light: Some(basic_light(Rgb24::new(128, 128, 0))),
                             ..Default::default()
                         },
                     ));
@@ -175,6 +634,15 @@ fn cell_grid_to_terrain_description<R: Rng>(
             Base::BrickWall => {
                 instructions.push(SetBackground(coord, BackgroundTile::BrickWall))
             }
+            Base::Water => instructions.push(SetBackground(coord, BackgroundTile::Water)),
+            Base::Lava => instructions.push(SetBackground(coord, BackgroundTile::Lava)),
+            Base::Chasm => instructions.push(SetBackground(coord, BackgroundTile::Chasm)),
+            Base::Door => {
+                instructions.push(SetBackground(coord, BackgroundTile::DoorClosed))
+            }
+            Base::CrackedWall => {
+                instructions.push(SetBackground(coord, BackgroundTile::CrackedWall))
+            }
             Base::Tree => {
                 instructions.push(SetBackground(coord, BackgroundTile::Ground));
                 instructions.push(AddEntity(
@@ -190,18 +658,13 @@ fn cell_grid_to_terrain_description<R: Rng>(
     TerrainDescription::new(player_coord.unwrap(), grid.size(), instructions)
 }
 
-fn char_to_base(ch: char) -> Option<Base> {
-    match ch {
-        '.' => Some(Base::Floor),
-        ',' => Some(Base::Ground),
-        '#' | '$' | '?' => Some(Base::IceWall),
-        '&' | '%' => Some(Base::Tree),
-        _ => None,
-    }
-}
-
-fn char_to_cell<R: Rng>(ch: char, config: &Config, rng: &mut R) -> Option<Cell> {
-    if let Some(base) = char_to_base(ch) {
+fn char_to_cell<R: Rng>(
+    ch: char,
+    palette: &Palette,
+    config: &Config,
+    rng: &mut R,
+) -> Option<Cell> {
+    if let Some(base) = palette.base_of_char(ch) {
         Some(Cell::new(base))
     } else {
         match ch {
@@ -209,6 +672,8 @@ fn char_to_cell<R: Rng>(ch: char, config: &Config, rng: &mut R) -> Option<Cell>
             'd' => Some(Cell::new(Base::Floor).with_contents(Contents::Bruiser)),
             'c' => Some(Cell::new(Base::Floor).with_contents(Contents::Caster)),
             'h' => Some(Cell::new(Base::Floor).with_contents(Contents::Healer)),
+            'D' => Some(Cell::new(Base::Floor).with_contents(Contents::HeavyBruiser)),
+            'C' => Some(Cell::new(Base::Floor).with_contents(Contents::HeavyCaster)),
             '1' => Some(
                 Cell::new(Base::Floor).with_contents(Contents::Light(rgb24(255, 0, 0))),
             ),
@@ -220,6 +685,7 @@ fn char_to_cell<R: Rng>(ch: char, config: &Config, rng: &mut R) -> Option<Cell>
             ),
             'f' => Some(Cell::new(Base::Floor).with_contents(Contents::Flame)),
             '+' => Some(Cell::new(Base::Floor).with_contents(Contents::HealthPickup)),
+            'k' => Some(Cell::new(Base::Floor).with_contents(Contents::Key)),
             'a' => Some(
                 Cell::new(Base::Floor)
                     .with_contents(Contents::Altar)
@@ -235,9 +701,11 @@ fn char_to_cell<R: Rng>(ch: char, config: &Config, rng: &mut R) -> Option<Cell>
     }
 }
 
-fn char_grid_to_base_grid(char_grid: &Grid<char>) -> Grid<Base> {
+fn char_grid_to_base_grid(char_grid: &Grid<char>, palette: &Palette) -> Grid<Base> {
     Grid::new_grid_map_ref(char_grid, |&ch| {
-        char_to_base(ch).expect(&format!("unrecognised char: {}", ch))
+        palette
+            .base_of_char(ch)
+            .expect(&format!("unrecognised char: {}", ch))
     })
 }
 
@@ -246,8 +714,10 @@ fn char_grid_to_cell_grid<R: Rng>(
     config: &Config,
     rng: &mut R,
 ) -> Grid<Cell> {
+    let palette = Palette::default();
     Grid::new_grid_map_ref(char_grid, |&ch| {
-        char_to_cell(ch, config, rng).expect(&format!("unrecognised char: {}", ch))
+        char_to_cell(ch, &palette, config, rng)
+            .expect(&format!("unrecognised char: {}", ch))
     })
 }
 
@@ -342,17 +812,19 @@ where
 
 struct BadLevel;
 const MIN_ACCESSIBLE_CELLS: usize = 500;
+// reject levels with more than 1 hazard cell for every this many accessible cells
+const MAX_HAZARD_RATIO_DENOM: usize = 4;
 const NUM_STAIRS_CANDIDATES: usize = 100;
+const MAX_DOORWAY_WIDTH: usize = 3;
 
 fn populate_base_grid<R: Rng>(
     base_grid: &Grid<Base>,
     config: &Config,
+    objective: Option<Objective>,
+    include_up_stairs: bool,
     rng: &mut R,
 ) -> Result<Grid<Cell>, BadLevel> {
-    let mut areas = classify(base_grid, |&base| match base {
-        Base::Floor | Base::Ground => true,
-        Base::IceWall | Base::Tree | Base::StoneWall | Base::BrickWall => false,
-    });
+    let mut areas = classify(base_grid, |&base| base.is_passable());
     let (to_keep, to_fill) = if let Some(last) = areas.pop() {
         (last, areas)
     } else {
@@ -361,6 +833,13 @@ fn populate_base_grid<R: Rng>(
     if to_keep.len() < MIN_ACCESSIBLE_CELLS {
         return Err(BadLevel);
     }
+    let num_hazards = base_grid
+        .iter()
+        .filter(|&&base| base == Base::Lava || base == Base::Chasm)
+        .count();
+    if num_hazards * MAX_HAZARD_RATIO_DENOM > to_keep.len() {
+        return Err(BadLevel);
+    }
     let mut cell_grid = base_grid_to_default_cell_grid(base_grid);
     for &coord in to_fill.iter().flat_map(|a| a.iter()) {
         cell_grid.get_checked_mut(coord).base = config.wall;
@@ -370,14 +849,8 @@ fn populate_base_grid<R: Rng>(
             cell.base = config.wall;
         }
     }
-    let distance_map = binary_distance_map(
-        &cell_grid,
-        |_coord, cell| match cell.base {
-            Base::StoneWall | Base::BrickWall | Base::IceWall | Base::Tree => true,
-            Base::Floor | Base::Ground => false,
-        },
-        |_, _| true,
-    );
+    let distance_map =
+        binary_distance_map(&cell_grid, |_coord, cell| !cell.base.is_open(), |_, _| true);
     let item_candidates = distance_map
         .enumerate()
         .filter_map(|(coord, &distance)| {
@@ -393,13 +866,24 @@ fn populate_base_grid<R: Rng>(
     }
     let player_coord = *item_candidates.choose(rng).unwrap();
     cell_grid.get_checked_mut(player_coord).contents = Some(Contents::Player);
+    if include_up_stairs {
+        // the way back up is right beside where the player arrives
+        let up_stairs_candidates = CardinalDirections
+            .into_iter()
+            .map(|direction| player_coord + direction.coord())
+            .filter(|&coord| {
+                cell_grid.get(coord).is_some_and(|cell| {
+                    cell.base.is_passable() && cell.contents.is_none()
+                })
+            })
+            .collect::<Vec<_>>();
+        let &up_stairs_coord = up_stairs_candidates.choose(rng).ok_or(BadLevel)?;
+        cell_grid.get_checked_mut(up_stairs_coord).contents = Some(Contents::UpStairs);
+    }
     let player_distance_map = binary_distance_map(
         &cell_grid,
         |_coord, cell| cell.contents == Some(Contents::Player),
-        |_coord, cell| match cell.base {
-            Base::StoneWall | Base::BrickWall | Base::IceWall | Base::Tree => false,
-            Base::Floor | Base::Ground => true,
-        },
+        |_coord, cell| cell.base.is_passable(),
     );
     let mut stairs_candidates = player_distance_map
         .enumerate()
@@ -426,21 +910,110 @@ fn populate_base_grid<R: Rng>(
     cell_grid.get_checked_mut(stairs_coord).contents = Some(if config.include_end {
         Contents::End
     } else {
-        Contents::Stairs
+        Contents::Stairs(Branch::Main)
     });
+    if config.include_end && config.include_stairs_with_end {
+        // leave a way further down, as far from the end as possible
+        let (extra_stairs_coord, _distance) = stairs_candidates
+            .iter()
+            .cloned()
+            .filter(|&(coord, _)| coord != stairs_coord)
+            .max_by_key(|&(coord, _)| coord.manhattan_distance(stairs_coord))
+            .ok_or(BadLevel)?;
+        cell_grid.get_checked_mut(extra_stairs_coord).contents =
+            Some(Contents::Stairs(Branch::Main));
+    }
+    // each side branch gets its own stairs, spread out from the other exits
+    let mut exit_coords = vec![stairs_coord];
+    for &branch in config.branches.iter() {
+        let (branch_stairs_coord, _distance) = stairs_candidates
+            .iter()
+            .cloned()
+            .filter(|&(coord, _)| cell_grid.get_checked(coord).contents.is_none())
+            .max_by_key(|&(coord, _)| {
+                exit_coords
+                    .iter()
+                    .map(|&exit_coord| coord.manhattan_distance(exit_coord))
+                    .min()
+            })
+            .ok_or(BadLevel)?;
+        cell_grid.get_checked_mut(branch_stairs_coord).contents =
+            Some(Contents::Stairs(branch));
+        exit_coords.push(branch_stairs_coord);
+    }
+    if config.include_boss {
+        // the boss guards the end, waiting nearby
+        let boss_coord = player_distance_map
+            .enumerate()
+            .filter_map(|(coord, &distance)| {
+                let stairs_distance = coord.manhattan_distance(stairs_coord);
+                if distance.is_some()
+                    && cell_grid.get_checked(coord).contents.is_none()
+                    && distance_map.get_checked(coord).unwrap() > 1
+                    && stairs_distance >= MIN_BOSS_DISTANCE_TO_END
+                {
+                    Some((coord, stairs_distance))
+                } else {
+                    None
+                }
+            })
+            .min_by_key(|&(_, stairs_distance)| stairs_distance)
+            .map(|(coord, _)| coord)
+            .ok_or(BadLevel)?;
+        cell_grid.get_checked_mut(boss_coord).contents = Some(Contents::Boss);
+    }
     let mut npc_candidates = item_candidates
         .iter()
         .cloned()
         .filter(|&coord| cell_grid.get_checked(coord).contents.is_none())
         .collect::<Vec<_>>();
     npc_candidates.shuffle(rng);
-    for _ in 0..config.npc_count {
+    if objective == Some(Objective::CollectKey) {
+        if let Some(coord) = npc_candidates.pop() {
+            cell_grid.get_checked_mut(coord).contents = Some(Contents::Key);
+        } else {
+            return Err(BadLevel);
+        }
+    }
+    for i in 0..config.npc_count {
         if let Some(coord) = npc_candidates.pop() {
             let npc = config.npc_dist.choose(rng).unwrap().clone();
-            cell_grid.get_checked_mut(coord).contents = Some(npc);
+            let cell = cell_grid.get_checked_mut(coord);
+            cell.contents = Some(npc);
+            if i < config.num_elites && !config.elite_traits.is_empty() {
+                let max_traits =
+                    config.max_traits_per_elite.min(config.elite_traits.len());
+                let num_traits = rng.gen_range(1, max_traits + 1);
+                cell.elite_traits = config
+                    .elite_traits
+                    .choose_multiple(rng, num_traits)
+                    .cloned()
+                    .collect();
+            }
+        }
+    }
+    for _ in 0..config.num_chests {
+        if let Some(coord) = npc_candidates.pop() {
+            cell_grid.get_checked_mut(coord).contents = Some(Contents::Chest);
+            if let Some(index) = npc_candidates.iter().position(|&guard_coord| {
+                guard_coord.manhattan_distance(coord) <= MAX_CHEST_GUARD_DISTANCE
+            }) {
+                let guard_coord = npc_candidates.swap_remove(index);
+                let guard = config.npc_dist.choose(rng).unwrap().clone();
+                let cell = cell_grid.get_checked_mut(guard_coord);
+                cell.contents = Some(guard);
+                if let Some(&elite_trait) = config.elite_traits.choose(rng) {
+                    cell.elite_traits = vec![elite_trait];
+                }
+            }
         }
     }
-    let upgrades = [Contents::Flame, Contents::Altar, Contents::Fountain];
+    let upgrades = [
+        Contents::Flame,
+        Contents::Altar,
+        Contents::Fountain,
+        Contents::Anvil,
+    ];
     for _ in 0..config.num_upgrades {
         if let Some(coord) = npc_candidates.pop() {
             let upgrade = upgrades.choose(rng).unwrap();
@@ -449,6 +1022,13 @@ fn populate_base_grid<R: Rng>(
             cell.upgrade = Some(Upgrade::new(&config.card_dist, rng));
         }
     }
+    for _ in 0..config.num_merchants {
+        if let Some(coord) = npc_candidates.pop() {
+            let cell = cell_grid.get_checked_mut(coord);
+            cell.contents = Some(Contents::Merchant);
+            cell.upgrade = Some(Upgrade::new(&config.card_dist, rng));
+        }
+    }
     for _ in 0..config.num_spikes {
         if let Some(coord) = npc_candidates.pop() {
             cell_grid.get_checked_mut(coord).contents = Some(Contents::NaturalSpike);
@@ -459,16 +1039,136 @@ fn populate_base_grid<R: Rng>(
             cell_grid.get_checked_mut(coord).contents = Some(Contents::HealthPickup);
         }
     }
-
+    for _ in 0..config.num_items {
+        if let Some(coord) = npc_candidates.pop() {
+            let item = *ALL_ITEMS.choose(rng).unwrap();
+            cell_grid.get_checked_mut(coord).contents = Some(Contents::Item(item));
+        }
+    }
+    place_doors(&mut cell_grid, config.num_doors, rng);
+    place_cracked_walls(&mut cell_grid, config.num_cracked_walls, rng);
     Ok(cell_grid)
 }
 
-fn wfc_base_grid<R: Rng>(input: &str, output_size: Size, rng: &mut R) -> Grid<Base> {
+fn is_open_at(cell_grid: &Grid<Cell>, coord: Coord) -> bool {
+    cell_grid
+        .get(coord)
+        .map(|cell| cell.base.is_open())
+        .unwrap_or(false)
+}
+
+fn is_wall_at(cell_grid: &Grid<Cell>, coord: Coord) -> bool {
+    cell_grid
+        .get(coord)
+        .map(|cell| cell.base.is_wall())
+        .unwrap_or(true)
+}
+
+/// Doors go in gaps in walls: short runs of empty floor bounded by walls at either end,
+/// with floor on both sides of the run. Each run becomes a single doorway.
+fn place_doors<R: Rng>(cell_grid: &mut Grid<Cell>, num_doors: u32, rng: &mut R) {
+    let axes = [
+        (Coord::new(1, 0), Coord::new(0, 1)),
+        (Coord::new(0, 1), Coord::new(1, 0)),
+    ];
+    let mut candidates = Vec::new();
+    for &(along, across) in axes.iter() {
+        for (start, cell) in cell_grid.enumerate() {
+            if !cell.base.is_wall() {
+                continue;
+            }
+            let mut run = Vec::new();
+            let mut coord = start + along;
+            while run.len() < MAX_DOORWAY_WIDTH
+                && is_open_at(cell_grid, coord)
+                && cell_grid.get_checked(coord).contents.is_none()
+                && is_open_at(cell_grid, coord + across)
+                && is_open_at(cell_grid, coord - across)
+            {
+                run.push(coord);
+                coord += along;
+            }
+            if !run.is_empty() && is_wall_at(cell_grid, coord) {
+                candidates.push(run);
+            }
+        }
+    }
+    candidates.shuffle(rng);
+    let mut num_placed = 0;
+    for run in candidates {
+        if num_placed >= num_doors {
+            break;
+        }
+        // doorways next to one another would leave nowhere to stand between them
+        let next_to_door = run.iter().any(|&coord| {
+            CardinalDirections.into_iter().any(|direction| {
+                cell_grid
+                    .get(coord + direction.coord())
+                    .map(|cell| cell.base == Base::Door)
+                    .unwrap_or(false)
+            })
+        });
+        if !next_to_door {
+            for coord in run {
+                cell_grid.get_checked_mut(coord).base = Base::Door;
+            }
+            num_placed += 1;
+        }
+    }
+}
+
+/// Cracked walls go in walls which separate two open cells, so breaking them opens a
+/// shortcut.
+fn place_cracked_walls<R: Rng>(
+    cell_grid: &mut Grid<Cell>,
+    num_cracked_walls: u32,
+    rng: &mut R,
+) {
+    let mut candidates = cell_grid
+        .enumerate()
+        .filter_map(|(coord, cell)| {
+            if !cell.base.is_wall() || cell.base == Base::Tree {
+                return None;
+            }
+            let open_between = |a, b| {
+                is_open_at(cell_grid, coord + a) && is_open_at(cell_grid, coord + b)
+            };
+            if open_between(Coord::new(0, -1), Coord::new(0, 1))
+                || open_between(Coord::new(-1, 0), Coord::new(1, 0))
+            {
+                Some(coord)
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    candidates.shuffle(rng);
+    for coord in candidates.into_iter().take(num_cracked_walls as usize) {
+        cell_grid.get_checked_mut(coord).base = Base::CrackedWall;
+    }
+}
+
+fn wfc_base_grid<R: Rng>(
+    sample: &WfcSample,
+    builtin: bool,
+    output_size: Size,
+    rng: &mut R,
+) -> Option<Grid<Base>> {
     struct Forbid {
         bottom_right_id: PatternId,
         ids_to_forbid_bottom_right: HashSet<PatternId>,
         ids_to_forbid_centre: HashSet<PatternId>,
         offset: i32,
+        builtin: bool,
+    }
+    impl Forbid {
+        // a sample supplied at runtime may contradict itself here, which makes the
+        // collapse fail, so the level is generated again
+        fn check<E: std::fmt::Debug>(&self, result: Result<(), E>) {
+            if self.builtin {
+                result.expect("builtin sample contradicts itself");
+            }
+        }
     }
     impl ForbidPattern for Forbid {
         fn forbid<W: Wrap, R: Rng>(&mut self, fi: &mut ForbidInterface<W>, rng: &mut R) {
@@ -477,13 +1177,16 @@ fn wfc_base_grid<R: Rng>(input: &str, output_size: Size, rng: &mut R) -> Grid<Ba
                 output_size.width() as i32 - self.offset,
                 output_size.height() as i32 - self.offset,
             );
-            fi.forbid_all_patterns_except(bottom_right_coord, self.bottom_right_id, rng)
-                .unwrap();
+            self.check(fi.forbid_all_patterns_except(
+                bottom_right_coord,
+                self.bottom_right_id,
+                rng,
+            ));
             for coord in XThenYIter::new(output_size) {
                 let delta = coord - bottom_right_coord;
                 if delta.magnitude2() > 2 {
                     for &id in self.ids_to_forbid_bottom_right.iter() {
-                        fi.forbid_pattern(coord, id, rng).unwrap();
+                        self.check(fi.forbid_pattern(coord, id, rng));
                     }
                 }
                 let pad = 6;
@@ -493,14 +1196,14 @@ fn wfc_base_grid<R: Rng>(input: &str, output_size: Size, rng: &mut R) -> Grid<Ba
                     && coord.y < output_size.height() as i32 - pad
                 {
                     for &id in self.ids_to_forbid_centre.iter() {
-                        fi.forbid_pattern(coord, id, rng).unwrap();
+                        self.check(fi.forbid_pattern(coord, id, rng));
                     }
                 }
             }
         }
     }
-    let pattern_size = 4;
-    let grid = string_to_char_grid(input);
+    let pattern_size = WFC_PATTERN_SIZE;
+    let grid = sample.chars.clone();
     let input_size = grid.size();
     // we will discard the bottom row and right column
     let virtual_output_size = output_size + Size::new(1, 1);
@@ -520,17 +1223,17 @@ fn wfc_base_grid<R: Rng>(input: &str, output_size: Size, rng: &mut R) -> Grid<Ba
         .iter()
         .cloned()
         .collect::<HashSet<_>>();
-    let top_left_ids = [
-        Coord::new(20, 0),
-        Coord::new(5, 26),
-        Coord::new(22, 54),
-        Coord::new(41, 53),
-        Coord::new(33, 56),
-    ]
-    .iter()
-    .flat_map(|&coord| id_grid.get_checked(coord).iter().cloned())
-    .collect::<HashSet<_>>();
-    for &empty_id in id_grid.get_checked(Coord::new(8, 8)).iter() {
+    // anchors are skipped if a sample supplied at runtime is too small to contain them
+    let top_left_ids = WFC_EDGE_ANCHORS
+        .iter()
+        .filter_map(|&coord| id_grid.get(coord))
+        .flat_map(|ids| ids.iter().cloned())
+        .collect::<HashSet<_>>();
+    let empty_ids = id_grid
+        .get(WFC_OPEN_FLOOR_ANCHOR)
+        .map(|ids| ids.iter().cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+    for empty_id in empty_ids {
         overlapping_patterns.pattern_mut(empty_id).clear_count();
     }
     let bottom_right_id = *id_grid
@@ -548,33 +1251,119 @@ fn wfc_base_grid<R: Rng>(input: &str, output_size: Size, rng: &mut R) -> Grid<Ba
         ids_to_forbid_bottom_right: bottom_right_ids,
         ids_to_forbid_centre: top_left_ids,
         offset: bottom_right_offset as i32,
+        builtin,
     };
     let mut run =
         RunBorrow::new_forbid(&mut context, &mut wave, &global_stats, forbid, rng);
-    run.collapse_retrying(NumTimes(10), rng).unwrap();
+    let collapsed = run.collapse_retrying(NumTimes(10), rng);
+    if builtin {
+        collapsed.unwrap();
+    } else {
+        collapsed.ok()?;
+    }
     let output_grid = Grid::new_fn(output_size, |coord| {
         let pattern_id = wave.grid().get_checked(coord).chosen_pattern_id().unwrap();
         *overlapping_patterns.pattern_top_left_value(pattern_id)
     });
-    char_grid_to_base_grid(&output_grid)
+    Some(char_grid_to_base_grid(&output_grid, &sample.palette))
 }
 
+/// Levels generated from a sample supplied at runtime before falling back to the builtin one.
+const MAX_SAMPLE_ATTEMPTS: u32 = 20;
+
 fn wfc_common<R: Rng>(
-    input: &str,
+    builtin_input: &str,
+    sample: Option<&WfcSample>,
     output_size: Size,
     config: Config,
+    include_up_stairs: bool,
     rng: &mut R,
 ) -> TerrainDescription {
+    let builtin = WfcSample::builtin(builtin_input);
+    let mut using_builtin = sample.is_none();
+    let mut sample = sample.unwrap_or(&builtin);
+    let objective = config.objectives.choose(rng).cloned();
+    let mut attempts = 0;
     let cell_grid = loop {
-        let base_grid = wfc_base_grid(input, output_size, rng);
-        if let Ok(cell_grid) = populate_base_grid(&base_grid, &config, rng) {
+        if attempts == MAX_SAMPLE_ATTEMPTS {
+            sample = &builtin;
+            using_builtin = true;
+        }
+        attempts += 1;
+        let base_grid = match wfc_base_grid(sample, using_builtin, output_size, rng) {
+            Some(base_grid) => base_grid,
+            None => continue,
+        };
+        if let Ok(cell_grid) =
+            populate_base_grid(&base_grid, &config, objective, include_up_stairs, rng)
+        {
             break cell_grid;
         }
     };
-    cell_grid_to_terrain_description(&cell_grid, rng)
+    cell_grid_to_terrain_description(&cell_grid, rng).with_objective(objective)
+}
+
+pub fn wfc<R: Rng>(
+    output_size: Size,
+    level: u32,
+    branch: Branch,
+    settings: &RunSettings,
+    sample: Option<&WfcSample>,
+    rng: &mut R,
+) -> TerrainDescription {
+    let (builtin_input, config) = branch_config(branch, level_config(level, settings));
+    wfc_common(
+        builtin_input,
+        sample,
+        output_size,
+        config.with_settings(settings),
+        level > 0,
+        rng,
+    )
+}
+
+/// The cards which can be found on a level, before it is generated.
+pub fn card_dist(level: u32, settings: &RunSettings) -> Vec<Card> {
+    level_config(level, settings).1.card_dist
+}
+
+const ICE_BRANCH_EXTRA_PICKUPS: u32 = 2;
+const RUINS_BRANCH_EXTRA_NPCS: u32 = 4;
+const RUINS_BRANCH_EXTRA_UPGRADES: u32 = 2;
+
+/// Side branches keep the depth's difficulty curve, but change its biome and population.
+fn branch_config(
+    branch: Branch,
+    (builtin_input, config): (&'static str, Config),
+) -> (&'static str, Config) {
+    match branch {
+        Branch::Main => (builtin_input, config),
+        Branch::Ice => (
+            include_str!("wfc_ice_cave.txt"),
+            Config {
+                wall: Base::IceWall,
+                npc_count: config.npc_count * 3 / 4,
+                num_elites: config.num_elites.saturating_sub(1),
+                num_pickups: config.num_pickups + ICE_BRANCH_EXTRA_PICKUPS,
+                ..config
+            },
+        ),
+        Branch::Ruins => (
+            include_str!("wfc_ruins.txt"),
+            Config {
+                wall: Base::BrickWall,
+                npc_count: config.npc_count + RUINS_BRANCH_EXTRA_NPCS,
+                num_elites: config.num_elites + 1,
+                num_upgrades: config.num_upgrades + RUINS_BRANCH_EXTRA_UPGRADES,
+                num_chests: config.num_chests + 1,
+                num_items: config.num_items + 1,
+                ..config
+            },
+        ),
+    }
 }
 
-pub fn wfc<R: Rng>(output_size: Size, level: u32, rng: &mut R) -> TerrainDescription {
+fn level_config(level: u32, settings: &RunSettings) -> (&'static str, Config) {
     use Card::*;
     use Contents::*;
     match level {
@@ -591,10 +1380,27 @@ pub fn wfc<R: Rng>(output_size: Size, level: u32, rng: &mut R) -> TerrainDescrip
                 ],
                 num_spikes: 8,
                 num_pickups: 4,
+                num_items: 2,
                 num_upgrades: 5,
+                num_chests: 1,
+                num_merchants: 1,
+                num_doors: 0,
+                num_cracked_walls: 4,
+                objectives: vec![
+                    Objective::ClearEnemies,
+                    Objective::ReachStairsWithin { turns: 150 },
+                    Objective::CollectKey,
+                    Objective::NoHealing,
+                ],
+                num_elites: 0,
+                elite_traits: Vec::new(),
+                max_traits_per_elite: 1,
                 include_end: false,
+                include_stairs_with_end: false,
+                include_boss: false,
+                branches: vec![Branch::Ice],
             };
-            wfc_common(include_str!("wfc_ice_cave.txt"), output_size, config, rng)
+            (include_str!("wfc_ice_cave.txt"), config)
         }
         1 => {
             let config = Config {
@@ -606,13 +1412,31 @@ pub fn wfc<R: Rng>(output_size: Size, level: u32, rng: &mut R) -> TerrainDescrip
                 card_dist: vec![
                     Bump, Heal, Freeze, Block, Spike, Empower, Blink, Spend, Spark,
                     Spark, Armour, Bash, Blink, Deposit, Deposit, Caltrop, Caltrop,
+                    Rampart, Poise,
                 ],
                 num_spikes: 10,
                 num_pickups: 4,
+                num_items: 2,
                 num_upgrades: 5,
+                num_chests: 1,
+                num_merchants: 1,
+                num_doors: 0,
+                num_cracked_walls: 6,
+                objectives: vec![
+                    Objective::ClearEnemies,
+                    Objective::ReachStairsWithin { turns: 150 },
+                    Objective::CollectKey,
+                    Objective::NoHealing,
+                ],
+                num_elites: 1,
+                elite_traits: vec![EliteTrait::Armoured, EliteTrait::Regenerating],
+                max_traits_per_elite: 1,
                 include_end: false,
+                include_stairs_with_end: false,
+                include_boss: false,
+                branches: vec![Branch::Ruins],
             };
-            wfc_common(include_str!("wfc_ice_cave.txt"), output_size, config, rng)
+            (include_str!("wfc_flooded_cave.txt"), config)
         }
         2 => {
             let config = Config {
@@ -624,89 +1448,256 @@ pub fn wfc<R: Rng>(output_size: Size, level: u32, rng: &mut R) -> TerrainDescrip
                 card_dist: vec![
                     Bump, Heal, Freeze, Block, Spike, Empower, Blink, Deposit, Caltrop,
                     Spend, Spark, Spark, Armour, Bash, Armour, Bash, Armour, Bash, Burn,
-                    Blast, Surround,
+                    Blast, Surround, Smash, Smash, Flicker, Poise, Rampart,
                 ],
                 num_spikes: 12,
                 num_pickups: 4,
+                num_items: 2,
                 num_upgrades: 5,
+                num_chests: 1,
+                num_merchants: 1,
+                num_doors: 6,
+                num_cracked_walls: 8,
+                objectives: vec![
+                    Objective::ClearEnemies,
+                    Objective::ReachStairsWithin { turns: 175 },
+                    Objective::CollectKey,
+                    Objective::NoHealing,
+                ],
+                num_elites: 2,
+                elite_traits: vec![
+                    EliteTrait::Armoured,
+                    EliteTrait::Regenerating,
+                    EliteTrait::Hasted,
+                    EliteTrait::Explosive,
+                ],
+                max_traits_per_elite: 1,
                 include_end: false,
+                include_stairs_with_end: false,
+                include_boss: false,
+                branches: vec![Branch::Ice, Branch::Ruins],
             };
-            wfc_common(include_str!("wfc_ruins.txt"), output_size, config, rng)
+            (include_str!("wfc_ruins.txt"), config)
         }
         3 => {
             let config = Config {
                 wall: Base::BrickWall,
                 npc_count: 18,
                 npc_dist: vec![
-                    Bruiser, Bruiser, Caster, Caster, Caster, Healer, Healer, Healer,
+                    Bruiser,
+                    Bruiser,
+                    Caster,
+                    Caster,
+                    Caster,
+                    Healer,
+                    Healer,
+                    Healer,
+                    HeavyBruiser,
                 ],
                 card_dist: vec![
                     Bump, Heal, Freeze, Block, Spike, Empower, Blink, Deposit, Caltrop,
                     Spend, Spark, Spark, Armour, Bash, Armour, Bash, Burn, Blast,
-                    Surround, Burn, Blast, Surround, Burn, Blast, Surround,
+                    Surround, Burn, Blast, Surround, Burn, Blast, Surround, Smash,
+                    Flicker, Poise,
                 ],
                 num_spikes: 12,
                 num_pickups: 4,
+                num_items: 2,
                 num_upgrades: 5,
+                num_chests: 1,
+                num_merchants: 1,
+                num_doors: 6,
+                num_cracked_walls: 8,
+                objectives: vec![
+                    Objective::ClearEnemies,
+                    Objective::ReachStairsWithin { turns: 175 },
+                    Objective::CollectKey,
+                    Objective::NoHealing,
+                ],
+                num_elites: 2,
+                elite_traits: ALL_ELITE_TRAITS.to_vec(),
+                max_traits_per_elite: 1,
                 include_end: false,
+                include_stairs_with_end: false,
+                include_boss: false,
+                branches: vec![Branch::Ruins],
             };
-            wfc_common(include_str!("wfc_ruins.txt"), output_size, config, rng)
+            (include_str!("wfc_chasm_ruins.txt"), config)
         }
         4 => {
             let config = Config {
                 wall: Base::StoneWall,
                 npc_count: 22,
                 npc_dist: vec![
-                    Bruiser, Bruiser, Caster, Caster, Caster, Healer, Healer, Healer,
+                    Bruiser,
+                    Bruiser,
+                    Caster,
+                    Caster,
+                    Caster,
+                    Healer,
+                    Healer,
+                    Healer,
+                    HeavyBruiser,
+                    HeavyCaster,
                 ],
                 card_dist: vec![
                     Spark, Burn, Save, Shred, Shred, Shred, Surround, Surround, Surround,
                     Recover, Recover, Armour, Armour, Bash, Bash, Bash, Deposit, Caltrop,
+                    Smash, Flicker, Poise,
                 ],
                 num_spikes: 20,
                 num_pickups: 4,
+                num_items: 2,
                 num_upgrades: 5,
+                num_chests: 1,
+                num_merchants: 1,
+                num_doors: 4,
+                num_cracked_walls: 6,
+                objectives: vec![
+                    Objective::ClearEnemies,
+                    Objective::ReachStairsWithin { turns: 200 },
+                    Objective::CollectKey,
+                    Objective::NoHealing,
+                ],
+                num_elites: 3,
+                elite_traits: ALL_ELITE_TRAITS.to_vec(),
+                max_traits_per_elite: 2,
                 include_end: false,
+                include_stairs_with_end: false,
+                include_boss: false,
+                branches: Vec::new(),
             };
-            wfc_common(include_str!("wfc_finale.txt"), output_size, config, rng)
+            (include_str!("wfc_magma.txt"), config)
         }
         5 => {
             let config = Config {
                 wall: Base::StoneWall,
                 npc_count: 24,
                 npc_dist: vec![
-                    Bruiser, Bruiser, Caster, Caster, Caster, Healer, Healer, Healer,
+                    Bruiser,
+                    Bruiser,
+                    Caster,
+                    Caster,
+                    Caster,
+                    Healer,
+                    Healer,
+                    Healer,
+                    HeavyBruiser,
+                    HeavyBruiser,
+                    HeavyCaster,
                 ],
                 card_dist: vec![
                     Spark, Burn, Save, Shred, Shred, Shred, Surround, Surround, Surround,
                     Recover, Recover, Armour, Armour, Bash, Bash, Bash, Deposit, Caltrop,
+                    Smash, Flicker, Poise,
                 ],
                 num_spikes: 20,
                 num_pickups: 4,
+                num_items: 2,
                 num_upgrades: 5,
+                num_chests: 1,
+                num_merchants: 1,
+                num_doors: 4,
+                num_cracked_walls: 6,
+                objectives: Vec::new(),
+                num_elites: 4,
+                elite_traits: ALL_ELITE_TRAITS.to_vec(),
+                max_traits_per_elite: 2,
                 include_end: true,
+                include_stairs_with_end: settings.mode == GameMode::Endless,
+                include_boss: true,
+                branches: Vec::new(),
             };
-            wfc_common(include_str!("wfc_finale.txt"), output_size, config, rng)
+            (include_str!("wfc_finale.txt"), config)
         }
         _ => {
+            // endless mode: difficulty grows with each level past the final one
+            let depth = level + 1 - NUM_LEVELS;
+            let (wall, builtin_input) =
+                ENDLESS_BIOMES[depth as usize % ENDLESS_BIOMES.len()];
+            let npc_count = (24 + 2 * depth).min(MAX_ENDLESS_NPC_COUNT);
+            let mut npc_dist = vec![
+                Bruiser,
+                Bruiser,
+                Caster,
+                Caster,
+                Caster,
+                Healer,
+                Healer,
+                HeavyBruiser,
+                HeavyCaster,
+            ];
+            npc_dist.extend(
+                [HeavyBruiser, HeavyCaster]
+                    .iter()
+                    .cycle()
+                    .take(depth.min(MAX_ENDLESS_EXTRA_HEAVIES) as usize)
+                    .cloned(),
+            );
+            let mut card_dist = vec![
+                Spark, Burn, Save, Shred, Shred, Surround, Surround, Recover, Armour,
+                Armour, Bash, Bash, Deposit, Caltrop, Smash, Flicker, Poise,
+            ];
+            card_dist.extend(
+                [Blast, Recover, Shred, Surround, Smash]
+                    .iter()
+                    .cycle()
+                    .take(depth.min(MAX_ENDLESS_EXTRA_CARDS) as usize)
+                    .cloned(),
+            );
             let config = Config {
-                wall: Base::IceWall,
-                npc_count: 12,
-                npc_dist: vec![
-                    Bruiser, Bruiser, Bruiser, Bruiser, Bruiser, Caster, Caster, Caster,
-                    Healer,
-                ],
-                card_dist: vec![Bump, Bump, Blink, Heal, Spark],
-                num_spikes: 8,
+                wall,
+                npc_count,
+                npc_dist,
+                card_dist,
+                num_spikes: (20 + depth).min(MAX_ENDLESS_SPIKES),
                 num_pickups: 4,
+                num_items: 2,
                 num_upgrades: 5,
+                num_chests: 1,
+                num_merchants: 1,
+                num_doors: 4,
+                num_cracked_walls: 6,
+                objectives: vec![
+                    Objective::ClearEnemies,
+                    Objective::ReachStairsWithin { turns: 200 },
+                    Objective::CollectKey,
+                    Objective::NoHealing,
+                ],
+                num_elites: (4 + depth / 2).min(npc_count),
+                elite_traits: ALL_ELITE_TRAITS.to_vec(),
+                max_traits_per_elite: 2 + depth as usize / 3,
                 include_end: false,
+                include_stairs_with_end: false,
+                include_boss: false,
+                branches: vec![Branch::Ice, Branch::Ruins],
             };
-            wfc_common(include_str!("wfc_ice_cave.txt"), output_size, config, rng)
+            (builtin_input, config)
         }
     }
 }
 
+/// Endless mode cycles through the biomes of the standard levels.
+const ENDLESS_BIOMES: &[(Base, &str)] = &[
+    (Base::IceWall, include_str!("wfc_ice_cave.txt")),
+    (Base::IceWall, include_str!("wfc_flooded_cave.txt")),
+    (Base::BrickWall, include_str!("wfc_ruins.txt")),
+    (Base::BrickWall, include_str!("wfc_chasm_ruins.txt")),
+    (Base::StoneWall, include_str!("wfc_magma.txt")),
+];
+const MAX_ENDLESS_NPC_COUNT: u32 = 40;
+const MAX_ENDLESS_EXTRA_HEAVIES: u32 = 8;
+const MAX_ENDLESS_EXTRA_CARDS: u32 = 10;
+const MAX_ENDLESS_SPIKES: u32 = 30;
+
+const ALL_ELITE_TRAITS: &[EliteTrait] = &[
+    EliteTrait::Armoured,
+    EliteTrait::Hasted,
+    EliteTrait::Regenerating,
+    EliteTrait::Explosive,
+    EliteTrait::LightAbsorbing,
+];
+
 struct Config {
     wall: Base,
     npc_count: u32,
@@ -714,11 +1705,47 @@ struct Config {
     card_dist: Vec<Card>,
     num_spikes: u32,
     num_upgrades: u32,
+    /// Relic chests, each watched over by a guard.
+    num_chests: u32,
+    num_merchants: u32,
     num_pickups: u32,
+    num_items: u32,
+    num_doors: u32,
+    num_cracked_walls: u32,
+    objectives: Vec<Objective>,
+    num_elites: u32,
+    elite_traits: Vec<EliteTrait>,
+    max_traits_per_elite: usize,
     include_end: bool,
+    /// Also place stairs on a level with an end, so the final victory is optional.
+    include_stairs_with_end: bool,
+    include_boss: bool,
+    /// Side branches leading off this level, each reached by its own stairs.
+    branches: Vec<Branch>,
 }
 
+const MIN_BOSS_DISTANCE_TO_END: u32 = 2;
+const MAX_CHEST_GUARD_DISTANCE: u32 = 3;
+
 impl Config {
+    fn with_settings(mut self, settings: &RunSettings) -> Self {
+        match settings.difficulty {
+            Difficulty::Easy => {
+                self.npc_count = self.npc_count * 3 / 4;
+                self.num_elites = self.num_elites.saturating_sub(1);
+            }
+            Difficulty::Normal => (),
+            Difficulty::Hard => {
+                self.npc_count = self.npc_count * 5 / 4;
+                self.num_elites += 1;
+                self.num_pickups /= 2;
+            }
+        }
+        if settings.has_mutator(Mutator::NoHealthPickups) {
+            self.num_pickups = 0;
+        }
+        self
+    }
     fn testing() -> Self {
         use Card::*;
         use Contents::*;
@@ -732,8 +1759,20 @@ impl Config {
             card_dist: vec![Bump, Bump, Blink, Heal, Spark],
             num_spikes: 8,
             num_upgrades: 4,
+            num_chests: 1,
+            num_merchants: 1,
             include_end: false,
+            include_stairs_with_end: false,
+            include_boss: false,
+            branches: Vec::new(),
             num_pickups: 4,
+            num_items: 2,
+            num_doors: 4,
+            num_cracked_walls: 4,
+            objectives: Vec::new(),
+            num_elites: 2,
+            elite_traits: ALL_ELITE_TRAITS.to_vec(),
+            max_traits_per_elite: 2,
         }
     }
 }
@@ -21,6 +21,10 @@ impl InputGrid for Visibility {
     }
 }
 
+// light absorbing elites dim everything within this radius
+const LIGHT_ABSORB_RADIUS: i32 = 2;
+const LIGHT_ABSORB_SCALE: u8 = 48;
+
 const VISION_DISTANCE_SQUARED: u32 = 160;
 const VISION_DISTANCE: vision_distance::Circle =
     vision_distance::Circle::new_squared(VISION_DISTANCE_SQUARED);
@@ -78,6 +82,12 @@ impl VisibileArea {
             false
         }
     }
+    pub fn is_discovered(&self, coord: Coord) -> bool {
+        self.grid
+            .get(coord)
+            .map(VisibilityCell::is_discovered)
+            .unwrap_or(false)
+    }
     pub fn light_colour(&self, coord: Coord) -> Rgb24 {
         if let Some(cell) = self.grid.get(coord) {
             if cell.last_lit == self.count {
@@ -89,6 +99,17 @@ impl VisibileArea {
             grey24(0)
         }
     }
+    /// Mark every cell as seen in the past, revealing the whole level on the map.
+    pub(crate) fn discover_all(&mut self) {
+        for cell in self.grid.iter_mut() {
+            if cell.last_seen == 0 {
+                cell.last_seen = 1;
+            }
+            if cell.last_lit == 0 {
+                cell.last_lit = 1;
+            }
+        }
+    }
     pub fn update(&mut self, player_coord: Coord, world: &World) {
         self.count += 1;
         let count = self.count;
@@ -137,6 +158,22 @@ impl VisibileArea {
                 },
             );
         }
+        for absorber_coord in world.light_absorber_coords() {
+            for y in -LIGHT_ABSORB_RADIUS..=LIGHT_ABSORB_RADIUS {
+                for x in -LIGHT_ABSORB_RADIUS..=LIGHT_ABSORB_RADIUS {
+                    let offset = Coord::new(x, y);
+                    if offset.magnitude2() as i32
+                        > LIGHT_ABSORB_RADIUS * LIGHT_ABSORB_RADIUS
+                    {
+                        continue;
+                    }
+                    if let Some(cell) = grid.get_mut(absorber_coord + offset) {
+                        cell.light_colour =
+                            cell.light_colour.normalised_scalar_mul(LIGHT_ABSORB_SCALE);
+                    }
+                }
+            }
+        }
         if let Some(ambient_light_floor) = AMBIENT_LIGHT_FLOOR {
             for cell in grid.iter_mut() {
                 cell.last_lit = count;
@@ -8,6 +8,31 @@ use rand::seq::*;
 use rgb24::*;
 use shadowcast::*;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BossPhase {
+    /// Chases and attacks like a bruiser.
+    Pursuit,
+    /// Alternates between chasing and slamming the ground around itself.
+    Shockwave,
+    /// Slams and periodically summons bruisers.
+    Frenzy,
+}
+
+/// A modifier which promotes an npc to an elite.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EliteTrait {
+    /// The first hit is absorbed by armour.
+    Armoured,
+    /// Acts twice each turn.
+    Hasted,
+    /// Recovers 1 hit point every few turns.
+    Regenerating,
+    /// Deals damage to everything adjacent when killed.
+    Explosive,
+    /// Darkens the area around it.
+    LightAbsorbing,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Upgrade {
     pub positive_cards: Vec<Card>,
@@ -62,7 +87,7 @@ pub struct HitPoints {
 }
 
 impl HitPoints {
-    fn new(current: u32, max: u32) -> Self {
+    pub(crate) fn new(current: u32, max: u32) -> Self {
         Self { current, max }
     }
 }
@@ -80,11 +105,19 @@ pub enum BackgroundTile {
     IceWall,
     BrickWall,
     StoneWall,
+    Water,
+    Lava,
+    Chasm,
+    DoorClosed,
+    DoorOpen,
+    CrackedWall,
+    Rubble,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ForegroundTile {
     HealthPickup,
+    Key,
     End,
     Spike,
     NaturalSpike,
@@ -92,6 +125,7 @@ pub enum ForegroundTile {
     Player,
     Tree,
     Stairs,
+    UpStairs,
     Bruiser,
     Caster,
     Healer,
@@ -100,7 +134,16 @@ pub enum ForegroundTile {
     Flame,
     Altar,
     Fountain,
+    Anvil,
+    Chest,
+    Bomb,
+    MappingScroll,
+    Torch,
+    TeleportCharm,
+    Gold,
+    Merchant,
     Spark,
+    Boss,
 }
 
 pub struct EntityIter<'a> {
@@ -132,7 +175,7 @@ impl<'a> Iterator for ForegroundTiles<'a> {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Light {
     coord: Coord,
     colour: Rgb24,
@@ -174,7 +217,7 @@ impl Light {
 pub type LightId = u64;
 pub type EntityId = u64;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entity {
     id: EntityId,
     coord: Coord,
@@ -188,19 +231,32 @@ pub struct Entity {
     hit_points: Option<HitPoints>,
     heal_countdown: Option<u32>,
     frozen: Option<u32>,
+    wading: bool,
     spike: bool,
     end: bool,
     pickup: bool,
     upgrade: Option<Upgrade>,
+    elite_traits: Vec<EliteTrait>,
+    armour: u32,
+    branch: Option<Branch>,
+    summoned: bool,
 }
 
 impl Entity {
     pub fn upgrade(&self) -> Option<&Upgrade> {
         self.upgrade.as_ref()
     }
+    /// The branch of the dungeon these stairs lead to.
+    pub fn branch(&self) -> Option<Branch> {
+        self.branch
+    }
     pub fn is_frozen(&self) -> bool {
         self.frozen.is_some()
     }
+    /// The number of turns a frozen entity will continue to skip.
+    pub fn frozen_turns(&self) -> Option<u32> {
+        self.frozen
+    }
     pub fn id(&self) -> EntityId {
         self.id
     }
@@ -228,6 +284,43 @@ impl Entity {
     pub fn heal_countdown(&self) -> Option<u32> {
         self.heal_countdown
     }
+    /// An npc wading through water loses every second turn.
+    pub fn is_wading(&self) -> bool {
+        self.wading
+    }
+    pub fn breaks_walls(&self) -> bool {
+        self.foreground_tile == Some(ForegroundTile::Bruiser)
+    }
+    pub fn is_boss(&self) -> bool {
+        self.foreground_tile == Some(ForegroundTile::Boss)
+    }
+    /// The boss becomes more dangerous as it loses hit points.
+    pub fn boss_phase(&self) -> Option<BossPhase> {
+        if !self.is_boss() {
+            return None;
+        }
+        let hit_points = self.hit_points?;
+        Some(if hit_points.current * 3 > hit_points.max * 2 {
+            BossPhase::Pursuit
+        } else if hit_points.current * 3 > hit_points.max {
+            BossPhase::Shockwave
+        } else {
+            BossPhase::Frenzy
+        })
+    }
+    pub fn elite_traits(&self) -> &[EliteTrait] {
+        &self.elite_traits
+    }
+    pub fn is_elite(&self) -> bool {
+        !self.elite_traits.is_empty()
+    }
+    pub fn has_elite_trait(&self, elite_trait: EliteTrait) -> bool {
+        self.elite_traits.contains(&elite_trait)
+    }
+    /// The number of hits which will be absorbed before this entity takes damage.
+    pub fn armour(&self) -> u32 {
+        self.armour
+    }
 }
 
 #[derive(Clone)]
@@ -244,6 +337,10 @@ pub struct PackedEntity {
     pub(crate) end: bool,
     pub(crate) pickup: bool,
     pub(crate) upgrade: Option<Upgrade>,
+    pub(crate) elite_traits: Vec<EliteTrait>,
+    pub(crate) branch: Option<Branch>,
+    /// Npcs summoned mid-fight leave no gold behind.
+    pub(crate) summoned: bool,
 }
 
 impl Default for PackedEntity {
@@ -261,6 +358,9 @@ impl Default for PackedEntity {
             end: false,
             upgrade: None,
             pickup: false,
+            elite_traits: Vec::new(),
+            branch: None,
+            summoned: false,
         }
     }
 }
@@ -325,6 +425,30 @@ impl PackedEntity {
             ..Default::default()
         }
     }
+    pub(crate) fn anvil() -> Self {
+        let light = PackedLight::new(rgb24(200, 180, 120), 30, Rational::new(1, 10));
+        Self {
+            foreground_tile: Some(ForegroundTile::Anvil),
+            light: Some(light),
+            npc: false,
+            player: false,
+            hit_points: Some(HitPoints::new(1, 1)),
+            interactive: true,
+            ..Default::default()
+        }
+    }
+    pub(crate) fn chest() -> Self {
+        let light = PackedLight::new(rgb24(220, 170, 50), 30, Rational::new(1, 10));
+        Self {
+            foreground_tile: Some(ForegroundTile::Chest),
+            light: Some(light),
+            npc: false,
+            player: false,
+            hit_points: Some(HitPoints::new(1, 1)),
+            interactive: true,
+            ..Default::default()
+        }
+    }
 
     pub(crate) fn blink() -> Self {
         let light = PackedLight::new(rgb24(0, 255, 255), 30, Rational::new(1, 10));
@@ -381,6 +505,54 @@ impl PackedEntity {
         }
     }
 
+    pub(crate) fn item(item: Item) -> Self {
+        Self {
+            foreground_tile: Some(item.foreground_tile()),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn gold() -> Self {
+        Self {
+            foreground_tile: Some(ForegroundTile::Gold),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn merchant(mut stock: Upgrade) -> Self {
+        stock.positive_cards.truncate(MERCHANT_NUM_CARDS);
+        stock
+            .character_upgrades
+            .truncate(MERCHANT_NUM_CHARACTER_UPGRADES);
+        let light = PackedLight::new(rgb24(200, 200, 80), 30, Rational::new(1, 10));
+        Self {
+            foreground_tile: Some(ForegroundTile::Merchant),
+            light: Some(light),
+            npc: false,
+            player: false,
+            hit_points: Some(HitPoints::new(1, 1)),
+            interactive: true,
+            upgrade: Some(stock),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn torch() -> Self {
+        let light = PackedLight::new(rgb24(255, 180, 80), 120, Rational::new(1, 10));
+        Self {
+            light: Some(light),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn key() -> Self {
+        Self {
+            foreground_tile: Some(ForegroundTile::Key),
+            pickup: true,
+            ..Default::default()
+        }
+    }
+
     pub(crate) fn player() -> Self {
         let player_light = PackedLight::new(grey24(128), 60, Rational::new(1, 10));
         Self {
@@ -415,6 +587,40 @@ impl PackedEntity {
             ..Default::default()
         }
     }
+    pub(crate) fn boss() -> Self {
+        let light = PackedLight::new(rgb24(255, 40, 0), 30, Rational::new(1, 10));
+        Self {
+            foreground_tile: Some(ForegroundTile::Boss),
+            light: Some(light),
+            npc: true,
+            hit_points: Some(HitPoints::new(12, 12)),
+            ..Default::default()
+        }
+    }
+    pub(crate) fn summoned_bumper() -> Self {
+        Self {
+            summoned: true,
+            ..Self::bumper()
+        }
+    }
+    pub(crate) fn heavy_bumper() -> Self {
+        Self {
+            hit_points: Some(HitPoints::new(5, 6)),
+            ..Self::bumper()
+        }
+    }
+    pub(crate) fn heavy_caster() -> Self {
+        Self {
+            hit_points: Some(HitPoints::new(4, 5)),
+            ..Self::caster()
+        }
+    }
+    pub(crate) fn with_elite_traits(self, elite_traits: Vec<EliteTrait>) -> Self {
+        Self {
+            elite_traits,
+            ..self
+        }
+    }
     pub(crate) fn healer() -> Self {
         Self {
             foreground_tile: Some(ForegroundTile::Healer),
@@ -459,7 +665,7 @@ impl PackedLight {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorldCell {
     background_tile: BackgroundTile,
     entities: HashSet<EntityId>,
@@ -500,6 +706,8 @@ impl WorldCell {
         self.background_tile == BackgroundTile::IceWall
             || self.background_tile == BackgroundTile::BrickWall
             || self.background_tile == BackgroundTile::StoneWall
+            || self.background_tile == BackgroundTile::CrackedWall
+            || self.background_tile == BackgroundTile::DoorClosed
             || self.interactive_count != 0
             || self.solid_count != 0
     }
@@ -518,6 +726,23 @@ impl WorldCell {
     pub fn is_end(&self) -> bool {
         self.end_count > 0
     }
+    pub fn is_water(&self) -> bool {
+        self.background_tile == BackgroundTile::Water
+    }
+    pub fn is_chasm(&self) -> bool {
+        self.background_tile == BackgroundTile::Chasm
+    }
+    pub fn is_closed_door(&self) -> bool {
+        self.background_tile == BackgroundTile::DoorClosed
+    }
+    pub fn is_cracked_wall(&self) -> bool {
+        self.background_tile == BackgroundTile::CrackedWall
+    }
+    /// Cells that npcs will not path through.
+    pub fn is_hazardous(&self) -> bool {
+        self.background_tile == BackgroundTile::Lava
+            || self.background_tile == BackgroundTile::Chasm
+    }
 }
 
 impl Default for WorldCell {
@@ -528,7 +753,7 @@ impl Default for WorldCell {
 
 pub type Entities = HashMap<EntityId, Entity>;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct World {
     grid: Grid<WorldCell>,
     lights: HashMap<LightId, Light>,
@@ -537,11 +762,19 @@ pub struct World {
     next_light_id: LightId,
     npc_ids: HashSet<EntityId>,
     remove_in_turns: HashMap<EntityId, u32>,
+    elite_kills: u32,
+    npc_spike_damage: u32,
 }
 
+const ELITE_ARMOUR: u32 = 1;
+const MERCHANT_NUM_CARDS: usize = 3;
+const MERCHANT_NUM_CHARACTER_UPGRADES: usize = 2;
+const EXPLOSION_DAMAGE: u32 = 1;
+
 #[derive(Debug)]
 pub enum CancelAction {
     MoveIntoSolidCell,
+    MoveIntoChasm,
     MoveIntoNpc,
     OutOfBounds,
     OutOfRange,
@@ -550,9 +783,16 @@ pub enum CancelAction {
     NoEntity,
     NoField,
     NothingToAttack,
+    NoDoor,
+    EndGuarded,
     AlreadyFullHitPoints,
     InvalidCard,
     NotEnoughEnergy,
+    NotEnoughGold,
+    MulliganUsed,
+    NoSuchItem,
+    NoTeleportDestination,
+    HandFull,
 }
 
 pub(crate) enum ApplyAction {
@@ -612,6 +852,8 @@ impl World {
             next_light_id: 0,
             npc_ids: HashSet::new(),
             remove_in_turns: HashMap::new(),
+            elite_kills: 0,
+            npc_spike_damage: 1,
         }
     }
     pub(crate) fn pack_entity(&self, id: EntityId) -> PackedEntity {
@@ -631,6 +873,9 @@ impl World {
             upgrade: entity.upgrade.clone(),
             end: entity.end,
             pickup: entity.pickup,
+            elite_traits: entity.elite_traits.clone(),
+            branch: entity.branch,
+            summoned: entity.summoned,
         }
     }
     pub(crate) fn lights(&self) -> &HashMap<LightId, Light> {
@@ -703,6 +948,9 @@ impl World {
             upgrade,
             end,
             pickup,
+            elite_traits,
+            branch,
+            summoned,
         } = entity;
         let id = self.next_id;
         self.next_id += 1;
@@ -728,10 +976,19 @@ impl World {
             heal_countdown: None,
             solid,
             frozen: None,
+            wading: false,
             spike,
             upgrade,
             end,
             pickup,
+            armour: if elite_traits.contains(&EliteTrait::Armoured) {
+                ELITE_ARMOUR
+            } else {
+                0
+            },
+            elite_traits,
+            branch,
+            summoned,
         };
         self.entities.insert(id, entity);
         if let Some(cell) = self.grid.get_mut(coord) {
@@ -779,7 +1036,80 @@ impl World {
         self.npc_ids.iter()
     }
 
-    const BLINK_RANGE: u32 = 8;
+    pub(crate) const BLINK_RANGE: u32 = 8;
+    const LAVA_DAMAGE: u32 = 1;
+
+    pub(crate) fn update_wading(&mut self, id: EntityId) {
+        if let Some(entity) = self.entities.get_mut(&id) {
+            let in_water = self
+                .grid
+                .get(entity.coord)
+                .map(|cell| cell.is_water())
+                .unwrap_or(false);
+            entity.wading = in_water && !entity.wading;
+        }
+    }
+
+    pub(crate) fn close_door_in_direction(
+        &mut self,
+        id: EntityId,
+        direction: CardinalDirection,
+    ) -> Result<ApplyAction, CancelAction> {
+        if let Some(entity) = self.entities.get(&id) {
+            let coord = entity.coord + direction.coord();
+            if let Some(cell) = self.grid.get_mut(coord) {
+                if cell.background_tile != BackgroundTile::DoorOpen {
+                    Err(CancelAction::NoDoor)
+                } else if !cell.entities.is_empty() {
+                    Err(CancelAction::LocationBlocked)
+                } else {
+                    cell.background_tile = BackgroundTile::DoorClosed;
+                    Ok(ApplyAction::Done)
+                }
+            } else {
+                Err(CancelAction::OutOfBounds)
+            }
+        } else {
+            Err(CancelAction::NoEntity)
+        }
+    }
+
+    pub(crate) fn smash_in_direction(
+        &mut self,
+        id: EntityId,
+        direction: CardinalDirection,
+    ) -> Result<ApplyAction, CancelAction> {
+        if let Some(entity) = self.entities.get(&id) {
+            let coord = entity.coord + direction.coord();
+            if let Some(cell) = self.grid.get_mut(coord) {
+                match cell.background_tile {
+                    BackgroundTile::CrackedWall
+                    | BackgroundTile::DoorClosed
+                    | BackgroundTile::DoorOpen => {
+                        cell.background_tile = BackgroundTile::Rubble;
+                        Ok(ApplyAction::Done)
+                    }
+                    _ => Err(CancelAction::NothingToAttack),
+                }
+            } else {
+                Err(CancelAction::OutOfBounds)
+            }
+        } else {
+            Err(CancelAction::NoEntity)
+        }
+    }
+
+    fn apply_terrain_effects(&mut self, id: EntityId) {
+        let coord = match self.entities.get(&id) {
+            Some(entity) if entity.hit_points.is_some() => entity.coord,
+            _ => return,
+        };
+        match self.grid.get(coord).map(|cell| cell.background_tile) {
+            Some(BackgroundTile::Lava) => self.deal_damage(id, Self::LAVA_DAMAGE),
+            Some(BackgroundTile::Chasm) => self.kill(id),
+            _ => (),
+        }
+    }
 
     pub(crate) fn spark_in_direction(
         &mut self,
@@ -799,16 +1129,19 @@ impl World {
         &mut self,
         id: EntityId,
         coord: Coord,
+        range: u32,
     ) -> Result<ApplyAction, CancelAction> {
         let result = if let Some(entity) = self.entities.get_mut(&id) {
             if let Some(cell) = self.grid.get(coord) {
                 if cell.is_solid() {
                     Err(CancelAction::MoveIntoSolidCell)
+                } else if cell.is_chasm() {
+                    Err(CancelAction::MoveIntoChasm)
                 } else if cell.contains_npc() {
                     Err(CancelAction::MoveIntoNpc)
                 } else {
                     let original_coord = entity.coord;
-                    if original_coord.manhattan_distance(coord) <= Self::BLINK_RANGE {
+                    if original_coord.manhattan_distance(coord) <= range {
                         move_entity_to_coord(
                             coord,
                             entity,
@@ -839,6 +1172,7 @@ impl World {
                     }
                 }
             }
+            self.apply_terrain_effects(id);
         }
         result
     }
@@ -869,6 +1203,7 @@ impl World {
         &mut self,
         id: EntityId,
         direction: CardinalDirection,
+        damage: u32,
     ) -> Result<ApplyAction, CancelAction> {
         if let Some(entity) = self.entities.get_mut(&id) {
             let coord = entity.coord + direction.coord();
@@ -878,7 +1213,9 @@ impl World {
                         .entity_iter(&self.entities)
                         .find_map(|e| if e.npc { Some(e.id) } else { None })
                         .unwrap();
-                    Ok(ApplyAction::Animation(Animation::damage(id, direction, 2)))
+                    Ok(ApplyAction::Animation(Animation::damage(
+                        id, direction, damage,
+                    )))
                 } else {
                     Err(CancelAction::NothingToAttack)
                 }
@@ -904,7 +1241,11 @@ impl World {
                         .find_map(|e| if e.npc { Some(e.id) } else { None })
                         .unwrap();
                     let _ = self.move_entity_in_direction(id, direction);
-                    Ok(ApplyAction::Animation(Animation::damage(id, direction, 1)))
+                    if self.entities.contains_key(&id) {
+                        Ok(ApplyAction::Animation(Animation::damage(id, direction, 1)))
+                    } else {
+                        Ok(ApplyAction::Done)
+                    }
                 } else {
                     Err(CancelAction::NothingToAttack)
                 }
@@ -965,12 +1306,13 @@ impl World {
                     None
                 };
                 if cell.contains_spike() {
-                    self.deal_damage(id, 1);
+                    self.deal_damage(id, self.spike_damage(id));
                 }
                 if let Some(pickup) = pickup {
                     self.process_pickup(id, pickup);
                 }
             }
+            self.apply_terrain_effects(id);
         }
     }
     fn process_pickup(&mut self, player_id: EntityId, pickup_id: EntityId) {
@@ -980,6 +1322,7 @@ impl World {
                     self.remove_entity(pickup_id);
                 }
             }
+            Some(ForegroundTile::Key) => self.remove_entity(pickup_id),
             _ => (),
         }
     }
@@ -998,6 +1341,14 @@ impl World {
                         .map(|e| e.id)
                         .unwrap();
                     Ok(ApplyAction::Interact(interactive_id))
+                } else if cell.is_closed_door() && (entity.player || entity.npc) {
+                    self.grid.get_checked_mut(coord).background_tile =
+                        BackgroundTile::DoorOpen;
+                    Ok(ApplyAction::Done)
+                } else if cell.is_cracked_wall() && entity.breaks_walls() {
+                    self.grid.get_checked_mut(coord).background_tile =
+                        BackgroundTile::Rubble;
+                    Ok(ApplyAction::Done)
                 } else if cell.is_solid() {
                     if entity.player
                         && cell
@@ -1008,6 +1359,8 @@ impl World {
                     } else {
                         Err(CancelAction::MoveIntoSolidCell)
                     }
+                } else if cell.is_chasm() {
+                    Err(CancelAction::MoveIntoChasm)
                 } else if entity.npc && cell.contains_npc() {
                     Err(CancelAction::MoveIntoNpc)
                 } else if (entity.npc && cell.contains_player())
@@ -1022,7 +1375,7 @@ impl World {
                     move_entity_to_coord(coord, entity, &mut self.grid, &mut self.lights);
                     if let Some(cell) = self.grid.get(coord) {
                         if cell.contains_spike() {
-                            self.deal_damage(id, 1);
+                            self.deal_damage(id, self.spike_damage(id));
                         } else if let Some(pickup) =
                             cell.entity_iter(&self.entities).find(|e| e.pickup)
                         {
@@ -1032,6 +1385,7 @@ impl World {
                             }
                         }
                     }
+                    self.apply_terrain_effects(id);
                     Ok(ApplyAction::Done)
                 }
             } else {
@@ -1049,6 +1403,13 @@ impl World {
             BackgroundTile::IceWall => 128,
             BackgroundTile::BrickWall => 255,
             BackgroundTile::StoneWall => 255,
+            BackgroundTile::Water => 0,
+            BackgroundTile::Lava => 0,
+            BackgroundTile::Chasm => 0,
+            BackgroundTile::DoorClosed => 255,
+            BackgroundTile::DoorOpen => 0,
+            BackgroundTile::CrackedWall => 255,
+            BackgroundTile::Rubble => 0,
         };
         let foreground = cell
             .entity_iter(&self.entities)
@@ -1058,6 +1419,7 @@ impl World {
                         ForegroundTile::Blink0 | ForegroundTile::Blink1 => 0,
                         ForegroundTile::Spark => 0,
                         ForegroundTile::HealthPickup => 0,
+                        ForegroundTile::Key => 0,
                         ForegroundTile::End => 0,
                         ForegroundTile::NaturalSpike => 0,
                         ForegroundTile::Spike => 0,
@@ -1066,9 +1428,19 @@ impl World {
                         ForegroundTile::Healer => 0,
                         ForegroundTile::Player => 0,
                         ForegroundTile::Stairs => 0,
+                        ForegroundTile::UpStairs => 0,
                         ForegroundTile::Flame => 0,
                         ForegroundTile::Altar => 0,
                         ForegroundTile::Fountain => 0,
+                        ForegroundTile::Anvil => 0,
+                        ForegroundTile::Chest => 0,
+                        ForegroundTile::Bomb => 0,
+                        ForegroundTile::MappingScroll => 0,
+                        ForegroundTile::Torch => 0,
+                        ForegroundTile::TeleportCharm => 0,
+                        ForegroundTile::Gold => 0,
+                        ForegroundTile::Merchant => 0,
+                        ForegroundTile::Boss => 0,
                         ForegroundTile::Bruiser => 0,
                         ForegroundTile::Tree => 128,
                     })
@@ -1103,15 +1475,110 @@ impl World {
             if entity.is_frozen() {
                 return;
             }
+            if entity.armour > 0 {
+                entity.armour -= 1;
+                return;
+            }
             if let Some(hit_points) = entity.hit_points.as_mut() {
                 hit_points.current = hit_points.current.saturating_sub(damage);
                 if hit_points.current == 0 {
-                    self.remove_entity(id);
+                    self.kill(id);
                 }
             }
         }
     }
 
+    fn kill(&mut self, id: EntityId) {
+        let (coord, drops_gold, explosive) = match self.entities.get(&id) {
+            Some(entity) => {
+                if entity.npc && entity.is_elite() {
+                    self.elite_kills += 1;
+                }
+                (
+                    entity.coord,
+                    entity.npc && !entity.summoned,
+                    entity.has_elite_trait(EliteTrait::Explosive),
+                )
+            }
+            None => return,
+        };
+        self.remove_entity(id);
+        // gold dropped into a chasm or lava would be lost anyway
+        if drops_gold && !self.grid.get_checked(coord).is_hazardous() {
+            self.add_entity(coord, PackedEntity::gold());
+        }
+        if explosive {
+            let to_damage = Directions
+                .into_iter()
+                .filter_map(|direction| self.grid.get(coord + direction.coord()))
+                .flat_map(|cell| cell.entity_iter(&self.entities))
+                .filter(|entity| entity.npc || entity.player)
+                .map(|entity| entity.id)
+                .collect::<Vec<_>>();
+            for id in to_damage {
+                self.deal_damage(id, EXPLOSION_DAMAGE);
+            }
+        }
+    }
+
+    /// A cell a summoned npc could appear in.
+    pub(crate) fn is_free_for_summon(&self, coord: Coord) -> bool {
+        self.grid.get(coord).is_some_and(|cell| {
+            !(cell.is_solid()
+                || cell.is_hazardous()
+                || cell.contains_npc()
+                || cell.contains_player())
+        })
+    }
+
+    /// Take something off a merchant's shelves once it has been bought.
+    pub(crate) fn remove_from_stock(&mut self, id: EntityId, purchase: Purchase) {
+        if let Some(stock) = self.entities.get_mut(&id).and_then(|e| e.upgrade.as_mut()) {
+            match purchase {
+                Purchase::Card(card) => {
+                    if let Some(index) =
+                        stock.positive_cards.iter().position(|&c| c == card)
+                    {
+                        stock.positive_cards.remove(index);
+                    }
+                }
+                Purchase::CharacterUpgrade(character_upgrade) => {
+                    if let Some(index) = stock
+                        .character_upgrades
+                        .iter()
+                        .position(|&u| u == character_upgrade)
+                    {
+                        stock.character_upgrades.remove(index);
+                    }
+                }
+                Purchase::RemoveCard(_) => (),
+            }
+        }
+    }
+
+    pub(crate) fn set_npc_spike_damage(&mut self, damage: u32) {
+        self.npc_spike_damage = damage;
+    }
+    fn spike_damage(&self, id: EntityId) -> u32 {
+        if self.npc_ids.contains(&id) {
+            self.npc_spike_damage
+        } else {
+            1
+        }
+    }
+    /// The number of elite npcs killed on this level.
+    pub(crate) fn elite_kills(&self) -> u32 {
+        self.elite_kills
+    }
+
+    pub(crate) fn light_absorber_coords(&self) -> impl '_ + Iterator<Item = Coord> {
+        self.npc_ids
+            .iter()
+            .map(move |id| self.entities.get(id).unwrap())
+            .filter(|entity| entity.has_elite_trait(EliteTrait::LightAbsorbing))
+            .map(Entity::coord)
+    }
+
     pub(crate) fn increase_max_hit_points(&mut self, id: EntityId, by: u32) {
         if let Some(entity) = self.entities.get_mut(&id) {
             if let Some(hit_points) = entity.hit_points.as_mut() {
@@ -1126,6 +1593,10 @@ impl World {
                 return;
             }
         }
+        self.remove_any_entity(id);
+    }
+    /// Unlike `remove_entity` this can remove the player, for when they leave the level.
+    pub(crate) fn remove_any_entity(&mut self, id: EntityId) {
         if let Some(entity) = self.entities.remove(&id) {
             if entity.npc {
                 self.npc_ids.remove(&id);
@@ -1135,6 +1606,9 @@ impl World {
                 if entity.npc {
                     cell.npc_count -= 1;
                 }
+                if entity.player {
+                    cell.player_count -= 1;
+                }
                 if entity.interactive {
                     cell.interactive_count -= 1;
                 }
@@ -1,25 +1,125 @@
-extern crate image;
+#[macro_use]
 extern crate simon;
+extern crate coord_2d;
+extern crate gws;
+extern crate image;
+extern crate rgb24;
+
+use coord_2d::{Coord, Size};
+use gws::{Palette, SampleError, WfcSample};
+use rgb24::rgb24;
+use std::fs;
+use std::process;
+
+struct Args {
+    in_path: String,
+    out_path: Option<String>,
+    palette_path: Option<String>,
+    reverse: bool,
+    check: bool,
+}
+
+impl Args {
+    fn arg() -> simon::ArgExt<impl simon::Arg<Item = Self>> {
+        args_map! {
+            let {
+                in_path = simon::opt_required("i", "in", "path to input file", "PATH");
+                out_path = simon::opt("o", "out", "path to output image (reverse mode)",
+                                      "PATH");
+                palette_path = simon::opt("p", "palette",
+                                          "palette mapping characters to colours",
+                                          "PATH");
+                reverse = simon::flag("r", "reverse", "convert a text file into an image");
+                check = simon::flag("c", "check",
+                                    "check that the sample can be used for terrain generation");
+            } in {
+                Self { in_path, out_path, palette_path, reverse, check }
+            }
+        }
+    }
+}
 
-use simon::*;
+fn exit_with_sample_error(path: &str, error: SampleError) -> ! {
+    match error {
+        SampleError::UnknownColours(unknown) => {
+            for (coord, colour) in unknown {
+                eprintln!(
+                    "{}: unrecognised colour #{:02x}{:02x}{:02x} at ({}, {})",
+                    path, colour.r, colour.g, colour.b, coord.x, coord.y
+                );
+            }
+        }
+        SampleError::UnknownChars(unknown) => {
+            for (coord, ch) in unknown {
+                eprintln!(
+                    "{}: unrecognised character {:?} at ({}, {})",
+                    path, ch, coord.x, coord.y
+                );
+            }
+        }
+        other => eprintln!("{}: {:?}", path, other),
+    }
+    process::exit(1);
+}
 
 fn main() {
-    let in_path = opt_required::<String>("i", "in", "path to input image file", "PATH")
-        .with_help_default()
-        .parse_env_default_or_exit();
-    let in_image = image::open(in_path).unwrap().to_rgb();
-    for y in 0..in_image.height() {
-        for x in 0..in_image.width() {
-            let [r, g, b] = in_image.get_pixel(x, y).data;
-            let ch = match (r, g, b) {
-                (0, 0, 0) => '#',
-                (255, 255, 255) => '.',
-                (0, 0, 255) => '$',
-                (255, 0, 0) => '?',
-                other => panic!("unrecognised colour: {:?}", other),
-            };
-            print!("{}", ch);
+    let args = Args::arg().with_help_default().parse_env_default_or_exit();
+    let palette = match args.palette_path.as_ref() {
+        Some(path) => {
+            let s = fs::read_to_string(path).unwrap();
+            Palette::parse(&s).unwrap_or_else(|e| {
+                eprintln!("{}: {:?}", path, e);
+                process::exit(1);
+            })
+        }
+        None => Palette::default(),
+    };
+    let sample = if args.reverse {
+        let s = fs::read_to_string(&args.in_path).unwrap();
+        WfcSample::from_text(&s, palette)
+    } else {
+        let in_image = image::open(&args.in_path).unwrap().to_rgb();
+        let size = Size::new(in_image.width(), in_image.height());
+        WfcSample::from_pixels(
+            size,
+            |coord| {
+                let [r, g, b] = in_image.get_pixel(coord.x as u32, coord.y as u32).data;
+                rgb24(r, g, b)
+            },
+            palette,
+        )
+    }
+    .unwrap_or_else(|e| exit_with_sample_error(&args.in_path, e));
+    if args.check {
+        let problems = sample.validate();
+        for problem in problems.iter() {
+            eprintln!("{}: {:?}", args.in_path, problem);
+        }
+        if !problems.is_empty() {
+            process::exit(1);
+        }
+    }
+    let chars = sample.chars();
+    if args.reverse {
+        let out_path = args.out_path.unwrap_or_else(|| {
+            eprintln!("--out is required in reverse mode");
+            process::exit(1);
+        });
+        let out_image =
+            image::ImageBuffer::from_fn(chars.width(), chars.height(), |x, y| {
+                let ch = *chars.get_checked(Coord::new(x as i32, y as i32));
+                let colour = sample.palette().colour_of_char(ch).unwrap();
+                image::Rgb {
+                    data: [colour.r, colour.g, colour.b],
+                }
+            });
+        out_image.save(out_path).unwrap();
+    } else {
+        for y in 0..chars.height() {
+            let line = (0..chars.width())
+                .map(|x| *chars.get_checked(Coord::new(x as i32, y as i32)))
+                .collect::<String>();
+            println!("{}", line);
         }
-        println!("");
     }
 }
@@ -1,12 +1,21 @@
 #[macro_use]
 extern crate simon;
+extern crate coord_2d;
+extern crate gws;
 extern crate gws_prototty;
+extern crate image;
+extern crate rgb24;
 extern crate whoami;
 
+use coord_2d::Size;
+use gws::{Palette, SampleProblem, WfcSample};
 use gws_prototty::FirstRngSeed;
+use rgb24::rgb24;
+use std::fmt::Debug;
 use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::process;
 
 const SAVE_BASE: &'static str = "user";
 
@@ -14,6 +23,20 @@ pub struct CommonArgs {
     rng_seed: FirstRngSeed,
     name: String,
     debug_terrain_file: Option<String>,
+    wfc_sample_file: Option<String>,
+    wfc_palette_file: Option<String>,
+}
+
+fn read_file(filename: &str) -> String {
+    let mut f = File::open(filename).unwrap();
+    let mut buffer = String::new();
+    f.read_to_string(&mut buffer).unwrap();
+    buffer
+}
+
+fn exit_with_error<E: Debug>(filename: &str, error: E) -> ! {
+    eprintln!("{}: {:?}", filename, error);
+    process::exit(1);
 }
 
 impl CommonArgs {
@@ -33,9 +56,15 @@ impl CommonArgs {
                 debug_terrain_file = simon::opt("t", "debug-terain-file",
                                                 "text file to influence terrain generation",
                                                 "FILE");
+                wfc_sample_file = simon::opt("w", "wfc-sample",
+                                             "image or text (.txt) file to generate every level from",
+                                             "FILE");
+                wfc_palette_file = simon::opt("p", "wfc-palette",
+                                              "palette mapping the wfc sample to terrain",
+                                              "FILE");
 
             } in {
-                Self { rng_seed, name, debug_terrain_file }
+                Self { rng_seed, name, debug_terrain_file, wfc_sample_file, wfc_palette_file }
             }
         }
     }
@@ -46,11 +75,47 @@ impl CommonArgs {
         self.rng_seed
     }
     pub fn debug_terrain_string(&self) -> Option<String> {
-        self.debug_terrain_file.as_ref().map(|filename| {
-            let mut f = File::open(filename).unwrap();
-            let mut buffer = String::new();
-            f.read_to_string(&mut buffer).unwrap();
-            buffer
+        self.debug_terrain_file
+            .as_ref()
+            .map(|filename| read_file(filename))
+    }
+    pub fn wfc_sample(&self) -> Option<WfcSample> {
+        self.wfc_sample_file.as_ref().map(|filename| {
+            let palette = match self.wfc_palette_file.as_ref() {
+                Some(palette_filename) => Palette::parse(&read_file(palette_filename))
+                    .unwrap_or_else(|e| exit_with_error(palette_filename, e)),
+                None => Palette::default(),
+            };
+            let is_text = Path::new(filename)
+                .extension()
+                .map(|extension| extension == "txt")
+                .unwrap_or(false);
+            let sample = if is_text {
+                WfcSample::from_text(&read_file(filename), palette)
+            } else {
+                let image = image::open(filename)
+                    .unwrap_or_else(|e| exit_with_error(filename, e))
+                    .to_rgb();
+                let size = Size::new(image.width(), image.height());
+                WfcSample::from_pixels(
+                    size,
+                    |coord| {
+                        let [r, g, b] =
+                            image.get_pixel(coord.x as u32, coord.y as u32).data;
+                        rgb24(r, g, b)
+                    },
+                    palette,
+                )
+            };
+            let sample = sample.unwrap_or_else(|e| exit_with_error(filename, e));
+            let problems = sample.validate();
+            for problem in problems.iter() {
+                eprintln!("{}: {:?}", filename, problem);
+            }
+            if problems.iter().any(SampleProblem::is_fatal) {
+                process::exit(1);
+            }
+            sample
         })
     }
 }
@@ -9,7 +9,17 @@ const FLOOR_FOREGROUND: Rgb24 = rgb24(120, 150, 240);
 const GROUND_BACKGROUND: Rgb24 = rgb24(0, 0, 0);
 const GROUND_FOREGROUND: Rgb24 = rgb24(255, 255, 255);
 const TREE_COLOUR: Rgb24 = rgb24(30, 200, 60);
+const WATER_BACKGROUND: Rgb24 = rgb24(0, 40, 120);
+const WATER_FOREGROUND: Rgb24 = rgb24(80, 160, 255);
+const LAVA_BACKGROUND: Rgb24 = rgb24(200, 40, 0);
+const LAVA_FOREGROUND: Rgb24 = rgb24(255, 200, 0);
+const CHASM_FOREGROUND: Rgb24 = rgb24(40, 40, 60);
 const STAIRS_COLOUR: Rgb24 = rgb24(220, 100, 50);
+const ICE_STAIRS_COLOUR: Rgb24 = rgb24(100, 180, 255);
+const RUINS_STAIRS_COLOUR: Rgb24 = rgb24(190, 110, 255);
+const DOOR_FOREGROUND: Rgb24 = rgb24(220, 160, 60);
+const DOOR_BACKGROUND: Rgb24 = rgb24(80, 40, 10);
+const RUBBLE_FOREGROUND: Rgb24 = rgb24(140, 120, 100);
 
 const ICE_WALL_TOP_COLOUR: Rgb24 = rgb24(60, 80, 120);
 const ICE_WALL_FRONT_COLOUR: Rgb24 = FLOOR_FOREGROUND;
@@ -56,6 +66,37 @@ const GROUND: ViewCell = ViewCell::new()
     .with_character('.')
     .with_foreground(GROUND_FOREGROUND)
     .with_background(GROUND_BACKGROUND);
+const WATER: ViewCell = ViewCell::new()
+    .with_character('~')
+    .with_foreground(WATER_FOREGROUND)
+    .with_background(WATER_BACKGROUND);
+const LAVA: ViewCell = ViewCell::new()
+    .with_character('~')
+    .with_bold(true)
+    .with_foreground(LAVA_FOREGROUND)
+    .with_background(LAVA_BACKGROUND);
+const CHASM: ViewCell = ViewCell::new()
+    .with_character(':')
+    .with_foreground(CHASM_FOREGROUND)
+    .with_background(rgb24(0, 0, 0));
+const DOOR_CLOSED: ViewCell = ViewCell::new()
+    .with_character('+')
+    .with_bold(true)
+    .with_foreground(DOOR_FOREGROUND)
+    .with_background(DOOR_BACKGROUND);
+const DOOR_OPEN: ViewCell = ViewCell::new()
+    .with_character('\'')
+    .with_bold(true)
+    .with_foreground(DOOR_FOREGROUND)
+    .with_background(FLOOR_BACKGROUND);
+const CRACKED_WALL: ViewCell = ViewCell::new()
+    .with_character('▒')
+    .with_foreground(rgb24(160, 140, 120))
+    .with_background(rgb24(60, 50, 40));
+const RUBBLE: ViewCell = ViewCell::new()
+    .with_character(',')
+    .with_foreground(RUBBLE_FOREGROUND)
+    .with_background(FLOOR_BACKGROUND);
 const TREE: ViewCell = ViewCell::new()
     .with_character('♣')
     .with_bold(true)
@@ -64,6 +105,19 @@ const STAIRS: ViewCell = ViewCell::new()
     .with_character('>')
     .with_bold(true)
     .with_foreground(STAIRS_COLOUR);
+
+const UP_STAIRS: ViewCell = ViewCell::new()
+    .with_character('<')
+    .with_bold(true)
+    .with_foreground(rgb24(220, 220, 120));
+
+pub fn branch_colour(branch: Branch) -> Rgb24 {
+    match branch {
+        Branch::Main => STAIRS_COLOUR,
+        Branch::Ice => ICE_STAIRS_COLOUR,
+        Branch::Ruins => RUINS_STAIRS_COLOUR,
+    }
+}
 const PLAYER: ViewCell = ViewCell::new().with_character('@').with_bold(true);
 
 const END: ViewCell = ViewCell::new()
@@ -81,6 +135,11 @@ const CASTER_VIEW_CELL: ViewCell = ViewCell::new()
     .with_bold(true)
     .with_foreground(rgb24(30, 200, 80));
 
+const BOSS_CHAR: char = 'ж';
+const BOSS_VIEW_CELL: ViewCell = ViewCell::new()
+    .with_bold(true)
+    .with_foreground(rgb24(255, 120, 0));
+
 const HEALER_CHAR: char = 'h';
 const HEALER_VIEW_CELL: ViewCell = ViewCell::new()
     .with_bold(true)
@@ -95,6 +154,13 @@ const MOVE_VIEW_CELL: ViewCell = ViewCell::new()
 const ATTACK_VIEW_CELL: ViewCell = ViewCell::new()
     .with_bold(false)
     .with_foreground(rgb24(0, 255, 255));
+const SLAM_VIEW_CELL: ViewCell = ViewCell::new()
+    .with_bold(true)
+    .with_foreground(rgb24(255, 80, 0))
+    .with_background(rgb24(80, 0, 0));
+const SUMMON_VIEW_CELL: ViewCell = ViewCell::new()
+    .with_bold(true)
+    .with_foreground(rgb24(200, 20, 80));
 const HEAL_VIEW_CELL: ViewCell = ViewCell::new()
     .with_bold(true)
     .with_foreground(rgb24(200, 200, 0));
@@ -129,6 +195,46 @@ const FOUNTAIN: ViewCell = ViewCell::new()
     .with_bold(true)
     .with_foreground(rgb24(50, 100, 200));
 
+const ANVIL: ViewCell = ViewCell::new()
+    .with_character('π')
+    .with_bold(true)
+    .with_foreground(rgb24(200, 180, 120));
+
+const CHEST: ViewCell = ViewCell::new()
+    .with_character('¤')
+    .with_bold(true)
+    .with_foreground(rgb24(220, 170, 50));
+
+const BOMB: ViewCell = ViewCell::new()
+    .with_character('●')
+    .with_bold(true)
+    .with_foreground(rgb24(200, 60, 40));
+
+const MAPPING_SCROLL: ViewCell = ViewCell::new()
+    .with_character('§')
+    .with_bold(true)
+    .with_foreground(rgb24(220, 210, 160));
+
+const TORCH: ViewCell = ViewCell::new()
+    .with_character('¡')
+    .with_bold(true)
+    .with_foreground(rgb24(255, 180, 80));
+
+const TELEPORT_CHARM: ViewCell = ViewCell::new()
+    .with_character('♦')
+    .with_bold(true)
+    .with_foreground(rgb24(160, 80, 255));
+
+const GOLD: ViewCell = ViewCell::new()
+    .with_character('$')
+    .with_bold(true)
+    .with_foreground(rgb24(255, 215, 0));
+
+const MERCHANT: ViewCell = ViewCell::new()
+    .with_character('¥')
+    .with_bold(true)
+    .with_foreground(rgb24(200, 200, 80));
+
 const SPIKE: ViewCell = ViewCell::new()
     .with_character('▲')
     .with_bold(true)
@@ -143,6 +249,10 @@ const HEALTH_PICKUP: ViewCell = ViewCell::new()
     .with_character('+')
     .with_bold(true)
     .with_foreground(rgb24(140, 0, 0));
+const KEY: ViewCell = ViewCell::new()
+    .with_character('♀')
+    .with_bold(true)
+    .with_foreground(rgb24(255, 210, 0));
 
 fn npc_view_cell(entity: &Entity) -> ViewCell {
     // TODO messy
@@ -150,19 +260,31 @@ fn npc_view_cell(entity: &Entity) -> ViewCell {
         ForegroundTile::Bruiser => (BRUISER_CHAR, BRUISER_VIEW_CELL),
         ForegroundTile::Caster => (CASTER_CHAR, CASTER_VIEW_CELL),
         ForegroundTile::Healer => (HEALER_CHAR, HEALER_VIEW_CELL),
+        ForegroundTile::Boss => (BOSS_CHAR, BOSS_VIEW_CELL),
         _ => panic!("not npc"),
     };
+    let upper = ch.to_uppercase().next().unwrap();
     match entity.hit_points().expect("missing hit points").current {
         0 => view_cell.with_character('?'),
         1 => view_cell.with_character(ch),
-        2 => view_cell.with_character(ch.to_uppercase().next().unwrap()),
-        3 => view_cell
-            .with_character(ch.to_uppercase().next().unwrap())
-            .with_underline(true),
-        _ => panic!("unexpected npc health"),
+        2 => view_cell.with_character(upper),
+        3 => view_cell.with_character(upper).with_underline(true),
+        n => view_cell
+            .with_character(upper)
+            .with_underline(true)
+            .with_background(extra_health_background(n - 3)),
     }
 }
 
+// npcs with more than 3 hit points get a red background which deepens with each extra
+// hit point, up to a limit
+const MAX_EXTRA_HEALTH_SHADES: u32 = 5;
+
+fn extra_health_background(extra_hit_points: u32) -> Rgb24 {
+    let shade = extra_hit_points.min(MAX_EXTRA_HEALTH_SHADES);
+    rgb24((60 + 30 * shade) as u8, 0, (10 * shade) as u8)
+}
+
 fn light_view_cell(view_cell: &mut ViewCell, light_colour: Rgb24) {
     if let Some(foreground) = view_cell.style.foreground.as_mut() {
         *foreground = foreground.normalised_mul(light_colour);
@@ -185,6 +307,13 @@ fn game_view_cell(to_render: &ToRender, cell: &WorldCell, coord: Coord) -> ViewC
     let view_cell = match cell.background_tile() {
         BackgroundTile::Floor => FLOOR,
         BackgroundTile::Ground => GROUND,
+        BackgroundTile::Water => WATER,
+        BackgroundTile::Lava => LAVA,
+        BackgroundTile::Chasm => CHASM,
+        BackgroundTile::DoorClosed => DOOR_CLOSED,
+        BackgroundTile::DoorOpen => DOOR_OPEN,
+        BackgroundTile::CrackedWall => CRACKED_WALL,
+        BackgroundTile::Rubble => RUBBLE,
         BackgroundTile::IceWall => {
             if let Some(cell_below) = to_render.world.grid().get(coord + Coord::new(0, 1))
             {
@@ -248,20 +377,39 @@ fn game_view_cell(to_render: &ToRender, cell: &WorldCell, coord: Coord) -> ViewC
             view_cell
         } else if let Some(foreground_tile) = entity.foreground_tile() {
             match foreground_tile {
-                ForegroundTile::Player => PLAYER,
+                ForegroundTile::Player => {
+                    match to_render.commitment_grid.get_checked(coord) {
+                        // warn the player that they're standing somewhere about to be hit
+                        Some((_, CommitmentType::Slam)) => PLAYER
+                            .with_background(SLAM_VIEW_CELL.style.background.unwrap()),
+                        _ => PLAYER,
+                    }
+                }
                 ForegroundTile::End => END,
                 ForegroundTile::Block => BLOCK,
                 ForegroundTile::Spike => SPIKE,
                 ForegroundTile::NaturalSpike => NATURAL_SPIKE,
                 ForegroundTile::Spark => SPARK,
                 ForegroundTile::HealthPickup => HEALTH_PICKUP,
+                ForegroundTile::Key => KEY,
                 ForegroundTile::Tree => TREE,
-                ForegroundTile::Stairs => STAIRS,
+                ForegroundTile::Stairs => STAIRS.with_foreground(branch_colour(
+                    entity.branch().unwrap_or(Branch::Main),
+                )),
+                ForegroundTile::UpStairs => UP_STAIRS,
                 ForegroundTile::Blink0 => BLINK0,
                 ForegroundTile::Blink1 => BLINK1,
                 ForegroundTile::Flame => FLAME,
                 ForegroundTile::Altar => ALTAR,
                 ForegroundTile::Fountain => FOUNTAIN,
+                ForegroundTile::Anvil => ANVIL,
+                ForegroundTile::Chest => CHEST,
+                ForegroundTile::Bomb => BOMB,
+                ForegroundTile::MappingScroll => MAPPING_SCROLL,
+                ForegroundTile::Torch => TORCH,
+                ForegroundTile::TeleportCharm => TELEPORT_CHARM,
+                ForegroundTile::Gold => GOLD,
+                ForegroundTile::Merchant => MERCHANT,
                 _ => panic!(),
             }
             .coalesce(view_cell)
@@ -278,6 +426,12 @@ fn game_view_cell(to_render: &ToRender, cell: &WorldCell, coord: Coord) -> ViewC
                     ATTACK_VIEW_CELL.with_character('*').coalesce(view_cell)
                 }
                 CommitmentType::Heal(_) => view_cell,
+                CommitmentType::Slam => {
+                    SLAM_VIEW_CELL.with_character('!').coalesce(view_cell)
+                }
+                CommitmentType::Summon => {
+                    SUMMON_VIEW_CELL.with_character('?').coalesce(view_cell)
+                }
             }
         } else {
             view_cell
@@ -14,13 +14,14 @@ mod menus;
 mod ui;
 
 use direction::*;
-use game_view::GameView;
+use game_view::{branch_colour, GameView};
 use map_view::MapView;
 use menus::*;
 use prototty::*;
 use rand::{FromEntropy, Rng, SeedableRng};
 use rand_isaac::IsaacRng;
 use std::marker::PhantomData;
+use std::mem;
 use std::time::Duration;
 use ui::*;
 
@@ -35,9 +36,15 @@ const TITLE_VIEW: StringViewSingleLine =
 pub struct AppView {
     menu_and_title_view: MenuInstanceView<menus::main::EntryView>,
     pause_menu_and_title_view: MenuInstanceView<pause::EntryView>,
+    new_game_view: MenuInstanceView<new_game::EntryView>,
+    class_view: MenuInstanceView<class::EntryView>,
     flame_view: MenuInstanceView<card::EntryView>,
     altar_view: MenuInstanceView<altar::EntryView>,
+    item_view: MenuInstanceView<item::EntryView>,
+    merchant_view: MenuInstanceView<merchant::EntryView>,
     fountain_view: MenuInstanceView<fountain::EntryView>,
+    bonus_view: MenuInstanceView<bonus::EntryView>,
+    reward_view: MenuInstanceView<reward::EntryView>,
     string_view_word_wrap: StringView<wrap::Word>,
 }
 
@@ -55,6 +62,9 @@ pub enum InitStatus {
 use frontend::Frontend;
 
 const SAVE_KEY: &'static str = "save";
+const BEST_DEPTH_KEY: &str = "best_depth";
+const NUM_BONUS_UPGRADE_CHOICES: usize = 3;
+const NUM_REWARD_CARD_CHOICES: usize = 3;
 
 #[derive(Serialize, Deserialize)]
 struct RngWithSeed {
@@ -71,12 +81,17 @@ struct GameState {
 
 impl GameState {
     fn new(
-        between_levels: Option<gws::BetweenLevels>,
+        between_levels: gws::BetweenLevels,
         mut rng_with_seed: RngWithSeed,
         debug_terrain_string: Option<&str>,
+        wfc_sample: Option<&gws::WfcSample>,
     ) -> Self {
-        let game =
-            gws::Gws::new(between_levels, &mut rng_with_seed.rng, debug_terrain_string);
+        let game = gws::Gws::new(
+            Some(between_levels),
+            &mut rng_with_seed.rng,
+            debug_terrain_string,
+            wfc_sample,
+        );
         Self {
             rng_with_seed,
             all_inputs: Vec::new(),
@@ -88,17 +103,26 @@ impl GameState {
 enum AppState {
     Game,
     Menu,
+    NewGameMenu,
+    ClassMenu,
     Map { opened_from_game: bool },
     Help { opened_from_game: bool },
     BetweenLevels(Option<gws::BetweenLevels>),
+    BonusMenu(gws::BetweenLevels),
+    RewardMenu(gws::BetweenLevels),
+    // choosing the card to remove or upgrade
+    RewardCardMenu(gws::BetweenLevels, reward::Entry),
     Death,
     CardMenu,
     AltarMenu,
     FountainMenu,
+    ItemMenu,
+    MerchantMenu,
     ListDeck,
     ListSpent,
     ListWaste,
     ListBurnt,
+    Overview,
     Story,
     ViewCursor,
     End(u32),
@@ -150,20 +174,219 @@ pub struct App<F: Frontend, S: Storage> {
     app_state: AppState,
     game_state: Option<GameState>,
     rng_source: RngSource,
+    run_settings: gws::RunSettings,
     menu: MenuInstance<main::Entry>,
     pause_menu: MenuInstance<pause::Entry>,
+    new_game_menu: MenuInstance<new_game::Entry>,
+    class_menu: MenuInstance<class::Entry>,
     time_until_next_auto_save: Duration,
     help: String,
     debug_terrain_string: Option<String>,
+    wfc_sample: Option<gws::WfcSample>,
     message: Option<String>,
     card_table: CardTable,
     card_selection: Option<CardInSlot>,
+    /// Hand slots chosen to be discarded, while a mulligan is being picked.
+    mulligan: Option<Vec<usize>>,
     card_menu_title: String,
     card_menu: Option<MenuInstance<gws::Card>>,
     altar_menu: Option<MenuInstance<altar::Entry>>,
     fountain_menu: Option<MenuInstance<fountain::Entry>>,
+    item_menu: Option<MenuInstance<item::Entry>>,
+    merchant_menu: Option<MenuInstance<merchant::Entry>>,
+    bonus_menu: Option<MenuInstance<bonus::Entry>>,
+    reward_menu: Option<MenuInstance<reward::Entry>>,
+    reward_card_menu: Option<MenuInstance<gws::Card>>,
     interactive: Option<gws::Interactive>,
     view_cursor: Option<Coord>,
+    closing_door: bool,
+    activity: Option<ActivityState>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Activity {
+    Travel(Coord),
+    Explore,
+    Rest,
+}
+
+// a multi-turn activity, interrupted when anything new comes into view or the player is hurt
+struct ActivityState {
+    activity: Activity,
+    hit_points: u32,
+    npcs_in_view: Vec<gws::EntityId>,
+    num_turns: u32,
+}
+
+impl ActivityState {
+    fn new(activity: Activity, game: &gws::Gws) -> Self {
+        Self {
+            activity,
+            hit_points: game.to_render().player.hit_points().unwrap().current,
+            npcs_in_view: game.visible_npc_ids().collect(),
+            num_turns: 0,
+        }
+    }
+    fn interruption(&mut self, game: &gws::Gws) -> Option<&'static str> {
+        let hit_points = game.to_render().player.hit_points().unwrap().current;
+        if hit_points < self.hit_points {
+            return Some("You are hurt!");
+        }
+        self.hit_points = hit_points;
+        let npcs_in_view = game.visible_npc_ids().collect::<Vec<_>>();
+        if npcs_in_view
+            .iter()
+            .any(|id| !self.npcs_in_view.contains(id))
+        {
+            return Some("You see an enemy!");
+        }
+        self.npcs_in_view = npcs_in_view;
+        None
+    }
+    fn step(&mut self, game: &mut gws::Gws) -> Result<gws::Input, &'static str> {
+        if let Some(message) = self.interruption(game) {
+            return Err(message);
+        }
+        let input = match self.activity {
+            Activity::Travel(coord) => game
+                .travel_direction(coord)
+                .map(gws::Input::Move)
+                .ok_or("Can't find a way there!"),
+            Activity::Explore => game
+                .explore_direction()
+                .map(gws::Input::Move)
+                .ok_or("There's nowhere left to explore."),
+            Activity::Rest => {
+                let draw_countdown = game.draw_countdown();
+                if self.num_turns > 0 && draw_countdown.current == draw_countdown.max {
+                    Err("You finish resting and draw a fresh hand.")
+                } else if game.visible_npc_ids().next().is_some() {
+                    Err("You can't rest with an enemy in view!")
+                } else {
+                    Ok(gws::input::WAIT)
+                }
+            }
+        }?;
+        self.num_turns += 1;
+        Ok(input)
+    }
+}
+
+fn card_num_to_index(card_num: char) -> usize {
+    card_num.to_digit(10).unwrap() as usize - 1
+}
+
+fn adjacent_direction(from: Coord, to: Coord) -> Option<CardinalDirection> {
+    CardinalDirections
+        .into_iter()
+        .find(|direction| from + direction.coord() == to)
+}
+
+fn is_mouse_input(input: ProtottyInput) -> bool {
+    matches!(
+        input,
+        Input::MouseMove { .. }
+            | Input::MousePress { .. }
+            | Input::MouseRelease { .. }
+            | Input::MouseScroll { .. }
+    )
+}
+
+fn describe(game: &gws::Gws, coord: Coord) -> Option<String> {
+    let to_render = game.to_render();
+    if let Some(cell) = to_render.world.grid().get(coord) {
+        if to_render.visible_area.is_visible(coord)
+            && to_render.visible_area.light_colour(coord) != grey24(0)
+        {
+            use gws::*;
+            if let Some(foreground_tile) =
+                cell.foreground_tiles(to_render.world.entities()).next()
+            {
+                match foreground_tile {
+                    ForegroundTile::Bruiser => Some("Bruiser".to_string()),
+                    ForegroundTile::End => {
+                        Some("Your search is finaly at an end.".to_string())
+                    }
+                    ForegroundTile::HealthPickup => Some("Health Potion".to_string()),
+                    ForegroundTile::Key => {
+                        Some("Key: pick it up to complete the objective.".to_string())
+                    }
+                    ForegroundTile::Caster => Some("Caster".to_string()),
+                    ForegroundTile::Healer => Some("Healer".to_string()),
+                    ForegroundTile::Boss => Some(
+                        "The Guardian: it must die before you may leave.".to_string(),
+                    ),
+                    ForegroundTile::Spike => Some("Spike".to_string()),
+                    ForegroundTile::NaturalSpike => Some("Natural Spike".to_string()),
+                    ForegroundTile::Spark => None,
+                    ForegroundTile::Blink0 => None,
+                    ForegroundTile::Blink1 => None,
+                    ForegroundTile::Player => Some("You".to_string()),
+                    ForegroundTile::Tree => Some("Tree".to_string()),
+                    ForegroundTile::Block => Some("Block".to_string()),
+                    ForegroundTile::UpStairs => {
+                        Some("Stairs back up to the previous level".to_string())
+                    }
+                    ForegroundTile::Stairs => {
+                        let branch = cell
+                            .entity_iter(to_render.world.entities())
+                            .find_map(Entity::branch)
+                            .unwrap_or(Branch::Main);
+                        Some(match branch {
+                            Branch::Main => "Stairs to the next level".to_string(),
+                            _ => format!(
+                                "Stairs to the {}: {}",
+                                branch_text(branch),
+                                branch_description(branch)
+                            ),
+                        })
+                    }
+                    ForegroundTile::Flame => Some("Cleansing Flame".to_string()),
+                    ForegroundTile::Altar => Some("Cursed Altar".to_string()),
+                    ForegroundTile::Fountain => Some("Plentiful Fountain".to_string()),
+                    ForegroundTile::Anvil => Some("Smith's Anvil".to_string()),
+                    ForegroundTile::Chest => Some("Relic Chest".to_string()),
+                    ForegroundTile::Bomb => Some(item_text(Item::Bomb).to_string()),
+                    ForegroundTile::MappingScroll => {
+                        Some(item_text(Item::MappingScroll).to_string())
+                    }
+                    ForegroundTile::Torch => Some(item_text(Item::Torch).to_string()),
+                    ForegroundTile::Gold => Some("Gold".to_string()),
+                    ForegroundTile::Merchant => Some("Merchant".to_string()),
+                    ForegroundTile::TeleportCharm => {
+                        Some(item_text(Item::TeleportCharm).to_string())
+                    }
+                }
+            } else {
+                match cell.background_tile() {
+                    BackgroundTile::Floor => Some("Floor".to_string()),
+                    BackgroundTile::Ground => Some("Ground".to_string()),
+                    BackgroundTile::IceWall => Some("Ice Wall".to_string()),
+                    BackgroundTile::StoneWall => Some("Stone Wall".to_string()),
+                    BackgroundTile::BrickWall => Some("Brick Wall".to_string()),
+                    BackgroundTile::Water => {
+                        Some("Water: moving through it is slow.".to_string())
+                    }
+                    BackgroundTile::Lava => {
+                        Some("Lava: burns whoever enters.".to_string())
+                    }
+                    BackgroundTile::Chasm => {
+                        Some("Chasm: enemies pushed in will fall.".to_string())
+                    }
+                    BackgroundTile::DoorClosed => Some("Closed Door".to_string()),
+                    BackgroundTile::DoorOpen => Some("Open Door".to_string()),
+                    BackgroundTile::CrackedWall => {
+                        Some("Cracked Wall: can be smashed through.".to_string())
+                    }
+                    BackgroundTile::Rubble => Some("Rubble".to_string()),
+                }
+            }
+        } else {
+            None
+        }
+    } else {
+        None
+    }
 }
 
 fn list_cards<G, R>(
@@ -185,7 +408,14 @@ fn list_cards<G, R>(
     );
     for (i, &card) in cards.iter().enumerate() {
         let info = card_table.get(card);
-        StringViewSingleLine::default().view(
+        let style = if card.is_upgraded() {
+            Style::new()
+                .with_bold(true)
+                .with_foreground(UPGRADED_CARD_COLOUR)
+        } else {
+            Style::default()
+        };
+        StringViewSingleLine::new(style).view(
             &format!("{}: {}", info.title, info.description),
             context.add_offset(Coord::new(1, i as i32 + 3)),
             grid,
@@ -193,6 +423,65 @@ fn list_cards<G, R>(
     }
 }
 
+fn reshuffle_message(game: &gws::Gws) -> String {
+    match game.reshuffle_rule() {
+        gws::ReshuffleRule::Waste => {
+            "Your wasted cards are shuffled back into your deck.".to_string()
+        }
+        gws::ReshuffleRule::Fatigue => format!(
+            "Your spent and wasted cards are shuffled back into your deck. Fatigue deals {} damage!",
+            game.reshuffles()
+        ),
+        _ => "Your spent and wasted cards are shuffled back into your deck.".to_string(),
+    }
+}
+
+fn view_overview<G, R>(game: &gws::Gws, context: ViewContext<R>, grid: &mut G)
+where
+    G: ViewGrid,
+    R: ViewTransformRgb24,
+{
+    StringViewSingleLine::default().view(
+        "Dungeon Overview",
+        context.add_offset(Coord::new(1, 1)),
+        grid,
+    );
+    let route = game.route();
+    for (i, &branch) in route.iter().enumerate() {
+        let text = if i + 1 == route.len() {
+            format!("Depth {}: {} (you are here)", i + 1, branch_text(branch))
+        } else {
+            format!("Depth {}: {}", i + 1, branch_text(branch))
+        };
+        StringViewSingleLine::new(Style::new().with_foreground(branch_colour(branch)))
+            .view(&text, context.add_offset(Coord::new(1, i as i32 + 3)), grid);
+    }
+    let mut offset = Coord::new(1, route.len() as i32 + 4);
+    StringViewSingleLine::default().view(
+        "Stairs found on this level:",
+        context.add_offset(offset),
+        grid,
+    );
+    let exits = game.discovered_exits();
+    if exits.is_empty() {
+        offset += Coord::new(0, 1);
+        StringViewSingleLine::default().view(
+            "None yet",
+            context.add_offset(offset),
+            grid,
+        );
+    }
+    for branch in exits {
+        offset += Coord::new(0, 1);
+        StringViewSingleLine::new(Style::new().with_foreground(branch_colour(branch)))
+            .view(
+                &format!("{}: {}", branch_text(branch), branch_description(branch)),
+                context.add_offset(offset),
+                grid,
+            );
+    }
+}
+
 const NUM_END_DIALOG: u32 = 7;
 
 impl<'a, F: Frontend, S: Storage> View<&'a App<F, S>> for AppView {
@@ -287,6 +576,7 @@ impl<'a, F: Frontend, S: Storage> View<&'a App<F, S>> for AppView {
                             message: app.message.as_ref().map(String::as_str),
                             card_table: &app.card_table,
                             card_selection: app.card_selection.as_ref(),
+                            mulligan: app.mulligan.as_deref(),
                             view_cursor: app.view_cursor.as_ref(),
                         },
                         context,
@@ -315,6 +605,9 @@ impl<'a, F: Frontend, S: Storage> View<&'a App<F, S>> for AppView {
                 context,
                 grid,
             ),
+            AppState::Overview => {
+                view_overview(&app.game_state.as_ref().unwrap().game, context, grid)
+            }
             AppState::ListBurnt => list_cards(
                 app.game_state.as_ref().unwrap().game.burnt(),
                 &app.card_table,
@@ -348,6 +641,41 @@ impl<'a, F: Frontend, S: Storage> View<&'a App<F, S>> for AppView {
                     grid,
                 );
             }
+            AppState::MerchantMenu => {
+                let gold = app
+                    .game_state
+                    .as_ref()
+                    .map(|game_state| game_state.game.gold())
+                    .unwrap_or(0);
+                StringViewSingleLine::new(
+                    Style::new().with_foreground(rgb24(200, 200, 80)),
+                )
+                .view(
+                    &format!("Merchant: Spend your gold. You have {} gold.", gold),
+                    context.add_offset(Coord::new(1, 1)),
+                    grid,
+                );
+                self.merchant_view.view(
+                    (app.merchant_menu.as_ref().unwrap(), &app.card_table),
+                    context.add_offset(Coord::new(1, 3)),
+                    grid,
+                );
+            }
+            AppState::ItemMenu => {
+                StringViewSingleLine::new(
+                    Style::new().with_foreground(rgb24(220, 170, 50)),
+                )
+                .view(
+                    "Items: Use an item.",
+                    context.add_offset(Coord::new(1, 1)),
+                    grid,
+                );
+                self.item_view.view(
+                    app.item_menu.as_ref().unwrap(),
+                    context.add_offset(Coord::new(1, 3)),
+                    grid,
+                );
+            }
             AppState::CardMenu => {
                 StringViewSingleLine::new(
                     Style::new().with_foreground(rgb24(255, 120, 0)),
@@ -371,6 +699,7 @@ impl<'a, F: Frontend, S: Storage> View<&'a App<F, S>> for AppView {
                             message: app.message.as_ref().map(String::as_str),
                             card_table: &app.card_table,
                             card_selection: app.card_selection.as_ref(),
+                            mulligan: app.mulligan.as_deref(),
                             view_cursor: None,
                         },
                         context.compose_transform_rgb24(|r: Rgb24| {
@@ -398,6 +727,34 @@ impl<'a, F: Frontend, S: Storage> View<&'a App<F, S>> for AppView {
                         context.add_offset(Coord::new(1, 3)).add_depth(1),
                         grid,
                     );
+                    // the run's settings, one per line, above the dimmed game
+                    let mut settings_view = StringViewSingleLine::new(
+                        Style::new()
+                            .with_foreground(rgb24(255, 255, 255))
+                            .with_background(rgb24(0, 0, 0)),
+                    );
+                    let settings = game_state.game.settings();
+                    let settings_lines =
+                        vec![
+                            format!(
+                                "{}, {} mode, {} difficulty",
+                                player_class_text(settings.player_class),
+                                mode_text(settings.mode),
+                                difficulty_text(settings.difficulty)
+                            ),
+                            format!("Reshuffle: {}", reshuffle_text(settings.reshuffle)),
+                        ]
+                        .into_iter()
+                        .chain(settings.mutators.iter().map(|&mutator| {
+                            format!("Mutator: {}", mutator_text(mutator))
+                        }));
+                    for (i, line) in settings_lines.enumerate() {
+                        settings_view.view(
+                            &line,
+                            context.add_offset(Coord::new(1, 1 + i as i32)).add_depth(1),
+                            grid,
+                        );
+                    }
                 } else {
                     TITLE_VIEW.view(TITLE, context.add_offset(Coord::new(1, 1)), grid);
                     self.menu_and_title_view.view(
@@ -407,6 +764,83 @@ impl<'a, F: Frontend, S: Storage> View<&'a App<F, S>> for AppView {
                     );
                 }
             }
+            AppState::ClassMenu => {
+                StringViewSingleLine::new(
+                    Style::new().with_foreground(TITLE_COLOUR).with_bold(true),
+                )
+                .view(
+                    "Choose a Class",
+                    context.add_offset(Coord::new(1, 1)),
+                    grid,
+                );
+                self.class_view.view(
+                    &app.class_menu,
+                    context.add_offset(Coord::new(1, 3)),
+                    grid,
+                );
+                let player_class = *app.class_menu.selected();
+                let mut offset = Coord::new(1, 5 + class::choices().len() as i32);
+                StringViewSingleLine::default().view(
+                    class::description(player_class),
+                    context.add_offset(offset),
+                    grid,
+                );
+                offset += Coord::new(0, 2);
+                StringViewSingleLine::default().view(
+                    &format!(
+                        "Life: {}  Hand: {}  Power: {}  Vision: {}",
+                        player_class.hit_points(),
+                        player_class.hand_size(),
+                        player_class.max_power(),
+                        player_class.light_range_squared(),
+                    ),
+                    context.add_offset(offset),
+                    grid,
+                );
+                offset += Coord::new(0, 2);
+                StringViewSingleLine::default().view(
+                    "Starting deck:",
+                    context.add_offset(offset),
+                    grid,
+                );
+                let mut deck = player_class.deck();
+                deck.sort();
+                deck.dedup();
+                for card in deck {
+                    let count =
+                        player_class.deck().iter().filter(|&&c| c == card).count();
+                    offset += Coord::new(0, 1);
+                    StringViewSingleLine::default().view(
+                        &format!("{} x {}", count, app.card_table.get(card).to_string()),
+                        context.add_offset(offset),
+                        grid,
+                    );
+                }
+            }
+            AppState::NewGameMenu => {
+                StringViewSingleLine::new(
+                    Style::new().with_foreground(TITLE_COLOUR).with_bold(true),
+                )
+                .view(
+                    "New Game",
+                    context.add_offset(Coord::new(1, 1)),
+                    grid,
+                );
+                self.new_game_view.view(
+                    (&app.new_game_menu, &app.run_settings),
+                    context.add_offset(Coord::new(1, 3)),
+                    grid,
+                );
+                StringViewSingleLine::default().view(
+                    new_game::description(
+                        *app.new_game_menu.selected(),
+                        &app.run_settings,
+                    ),
+                    context
+                        .add_offset(Coord::new(1, 5 + new_game::choices().len() as i32)),
+                    grid,
+                );
+            }
             AppState::Game => {
                 if let Some(game_state) = app.game_state.as_ref() {
                     UiView(GameView).view(
@@ -415,7 +849,8 @@ impl<'a, F: Frontend, S: Storage> View<&'a App<F, S>> for AppView {
                             message: app.message.as_ref().map(String::as_str),
                             card_table: &app.card_table,
                             card_selection: app.card_selection.as_ref(),
-                            view_cursor: None,
+                            mulligan: app.mulligan.as_deref(),
+                            view_cursor: app.view_cursor.as_ref(),
                         },
                         context,
                         grid,
@@ -430,6 +865,7 @@ impl<'a, F: Frontend, S: Storage> View<&'a App<F, S>> for AppView {
                             message: app.message.as_ref().map(String::as_str),
                             card_table: &app.card_table,
                             card_selection: None,
+                            mulligan: None,
                             view_cursor: None,
                         },
                         context,
@@ -447,6 +883,72 @@ impl<'a, F: Frontend, S: Storage> View<&'a App<F, S>> for AppView {
                     grid,
                 );
             }
+            AppState::BonusMenu(ref between_levels) => {
+                StringViewSingleLine::new(
+                    Style::new().with_foreground(rgb24(255, 210, 0)),
+                )
+                .view(
+                    &format!(
+                        "Bonus earned! Choose a bonus upgrade ({} remaining).",
+                        between_levels.bonus_upgrades()
+                    ),
+                    context.add_offset(Coord::new(1, 1)),
+                    grid,
+                );
+                self.bonus_view.view(
+                    app.bonus_menu.as_ref().unwrap(),
+                    context.add_offset(Coord::new(1, 3)),
+                    grid,
+                );
+            }
+            AppState::RewardMenu(_) => {
+                StringViewSingleLine::new(
+                    Style::new().with_foreground(rgb24(255, 210, 0)),
+                )
+                .view(
+                    "Level complete! Choose a reward.",
+                    context.add_offset(Coord::new(1, 1)),
+                    grid,
+                );
+                self.reward_view.view(
+                    (app.reward_menu.as_ref().unwrap(), &app.card_table),
+                    context.add_offset(Coord::new(1, 3)),
+                    grid,
+                );
+            }
+            AppState::RewardCardMenu(ref between_levels, choice) => {
+                let upgrade = matches!(choice, reward::Entry::Upgrade);
+                StringViewSingleLine::new(
+                    Style::new().with_foreground(rgb24(255, 210, 0)),
+                )
+                .view(
+                    if upgrade {
+                        "Choose a card to upgrade."
+                    } else {
+                        "Choose a card to remove."
+                    },
+                    context.add_offset(Coord::new(1, 1)),
+                    grid,
+                );
+                let reward_card_menu = app.reward_card_menu.as_ref().unwrap();
+                self.flame_view.view(
+                    (reward_card_menu, &app.card_table),
+                    context.add_offset(Coord::new(1, 3)),
+                    grid,
+                );
+                if let Some(upgraded) =
+                    reward_card_menu.selected().upgraded().filter(|_| upgrade)
+                {
+                    StringViewSingleLine::default().view(
+                        &format!("Becomes {}", app.card_table.get(upgraded).to_string()),
+                        context.add_offset(Coord::new(
+                            1,
+                            5 + reward::upgradable_cards(between_levels).len() as i32,
+                        )),
+                        grid,
+                    );
+                }
+            }
             AppState::Death => {
                 if let Some(game_state) = app.game_state.as_ref() {
                     DeathView.view(
@@ -455,6 +957,7 @@ impl<'a, F: Frontend, S: Storage> View<&'a App<F, S>> for AppView {
                             message: app.message.as_ref().map(String::as_str),
                             card_table: &app.card_table,
                             card_selection: None,
+                            mulligan: None,
                             view_cursor: None,
                         },
                         context,
@@ -477,6 +980,7 @@ impl<F: Frontend, S: Storage> App<F, S> {
         storage: S,
         first_rng_seed: FirstRngSeed,
         debug_terrain_string: Option<String>,
+        wfc_sample: Option<gws::WfcSample>,
     ) -> (Self, InitStatus) {
         let _ = frontend;
         let (init_status, game_state) = match storage.load::<_, GameState>(SAVE_KEY) {
@@ -489,26 +993,40 @@ impl<F: Frontend, S: Storage> App<F, S> {
         let rng_source = RngSource::new(first_rng_seed);
         let menu = MenuInstance::new(main::choices()).unwrap();
         let pause_menu = MenuInstance::new(pause::choices()).unwrap();
+        let new_game_menu = MenuInstance::new(new_game::choices()).unwrap();
+        let class_menu = MenuInstance::new(class::choices()).unwrap();
         let app = Self {
             frontend: PhantomData,
             storage,
             app_state: AppState::Menu,
             game_state,
             rng_source,
+            run_settings: Default::default(),
             menu,
             pause_menu,
+            new_game_menu,
+            class_menu,
             time_until_next_auto_save: AUTO_SAVE_PERIOD,
             help: include_str!("help.txt").to_string(),
             debug_terrain_string,
+            wfc_sample,
             message: None,
             card_table: CardTable::new(),
             card_selection: None,
+            mulligan: None,
             card_menu_title: "".to_string(),
             card_menu: None,
             altar_menu: None,
             fountain_menu: None,
+            item_menu: None,
+            merchant_menu: None,
+            bonus_menu: None,
+            reward_menu: None,
+            reward_card_menu: None,
             interactive: None,
             view_cursor: None,
+            closing_door: false,
+            activity: None,
         };
         (app, init_status)
     }
@@ -573,6 +1091,10 @@ impl<F: Frontend, S: Storage> App<F, S> {
                         to_render.player.coord()
                     };
                     let view_cursor = if let Some(input) = inputs.into_iter().next() {
+                        let hovered = match input {
+                            Input::MouseMove { coord, .. } => screen_to_game_coord(coord),
+                            _ => None,
+                        };
                         let delta = match input {
                             ProtottyInput::Up => Some(Coord::new(0, -1)),
                             ProtottyInput::Down => Some(Coord::new(0, 1)),
@@ -585,7 +1107,9 @@ impl<F: Frontend, S: Storage> App<F, S> {
                                 None
                             }
                         };
-                        if let Some(delta) = delta {
+                        if let Some(hovered) = hovered {
+                            hovered
+                        } else if let Some(delta) = delta {
                             let next_cursor = view_cursor + delta;
                             if next_cursor.is_valid(size) {
                                 next_cursor
@@ -598,76 +1122,20 @@ impl<F: Frontend, S: Storage> App<F, S> {
                     } else {
                         view_cursor
                     };
-                    self.view_cursor = Some(view_cursor);
-                    if let Some(cell) = to_render.world.grid().get(view_cursor) {
-                        if to_render.visible_area.is_visible(view_cursor)
-                            && to_render.visible_area.light_colour(view_cursor)
-                                != grey24(0)
-                        {
-                            use gws::*;
-                            if let Some(foreground_tile) =
-                                cell.foreground_tiles(to_render.world.entities()).next()
-                            {
-                                self.message = match foreground_tile {
-                                    ForegroundTile::Bruiser => {
-                                        Some("Bruiser".to_string())
-                                    }
-                                    ForegroundTile::End => Some(
-                                        "Your search is finaly at an end.".to_string(),
-                                    ),
-                                    ForegroundTile::HealthPickup => {
-                                        Some("Health Potion".to_string())
-                                    }
-                                    ForegroundTile::Caster => Some("Caster".to_string()),
-                                    ForegroundTile::Healer => Some("Healer".to_string()),
-                                    ForegroundTile::Spike => Some("Spike".to_string()),
-                                    ForegroundTile::NaturalSpike => {
-                                        Some("Natural Spike".to_string())
-                                    }
-                                    ForegroundTile::Spark => None,
-                                    ForegroundTile::Blink0 => None,
-                                    ForegroundTile::Blink1 => None,
-                                    ForegroundTile::Player => Some("You".to_string()),
-                                    ForegroundTile::Tree => Some("Tree".to_string()),
-                                    ForegroundTile::Block => Some("Block".to_string()),
-                                    ForegroundTile::Stairs => {
-                                        Some("Stairs to the next level".to_string())
-                                    }
-                                    ForegroundTile::Flame => {
-                                        Some("Cleansing Flame".to_string())
-                                    }
-                                    ForegroundTile::Altar => {
-                                        Some("Cursed Altar".to_string())
-                                    }
-                                    ForegroundTile::Fountain => {
-                                        Some("Plentiful Fountain".to_string())
-                                    }
-                                }
-                            } else {
-                                self.message = match cell.background_tile() {
-                                    BackgroundTile::Floor => Some("Floor".to_string()),
-                                    BackgroundTile::Ground => Some("Ground".to_string()),
-                                    BackgroundTile::IceWall => {
-                                        Some("Ice Wall".to_string())
-                                    }
-                                    BackgroundTile::StoneWall => {
-                                        Some("Stone Wall".to_string())
-                                    }
-                                    BackgroundTile::BrickWall => {
-                                        Some("Brick Wall".to_string())
-                                    }
-                                };
-                            }
-                        } else {
-                            self.message = None;
-                        }
+                    if let AppState::Game = self.app_state {
+                        self.view_cursor = None;
+                        self.message = None;
+                    } else {
+                        self.view_cursor = Some(view_cursor);
+                        self.message = describe(&game_state.game, view_cursor);
                     }
                 }
             }
             AppState::ListDeck
             | AppState::ListSpent
             | AppState::ListWaste
-            | AppState::ListBurnt => {
+            | AppState::ListBurnt
+            | AppState::Overview => {
                 for input in inputs {
                     match input {
                         Input::MouseMove { .. } => (),
@@ -717,6 +1185,83 @@ impl<F: Frontend, S: Storage> App<F, S> {
                     }
                 }
             }
+            AppState::MerchantMenu => {
+                match self
+                    .merchant_menu
+                    .as_mut()
+                    .unwrap()
+                    .tick_with_mouse(inputs, &view.merchant_view)
+                {
+                    None => (),
+                    Some(MenuOutput::Cancel) => {
+                        self.app_state = AppState::Game;
+                        self.interactive = None;
+                        self.merchant_menu = None;
+                    }
+                    Some(MenuOutput::Quit) => return Some(Tick::Quit),
+                    Some(MenuOutput::Finalise(&purchase)) => {
+                        let game_state = self.game_state.as_mut().unwrap();
+                        if game_state.game.gold() < purchase.price() {
+                            self.message = Some("Not enough gold!".to_string());
+                        } else {
+                            let input_start_index = game_state.all_inputs.len();
+                            let interactive = self.interactive.unwrap();
+                            let entity_id = interactive.entity_id;
+                            game_state.all_inputs.push(gws::input::interact(
+                                gws::InteractiveParam::Merchant {
+                                    entity_id,
+                                    purchase,
+                                },
+                            ));
+                            let input_end_index = game_state.all_inputs.len();
+                            let _ = game_state.game.tick(
+                                game_state.all_inputs[input_start_index..input_end_index]
+                                    .iter()
+                                    .cloned(),
+                                period,
+                                &mut game_state.rng_with_seed.rng,
+                            );
+                            self.message = None;
+                        }
+                        self.app_state = AppState::Game;
+                        self.interactive = None;
+                        self.merchant_menu = None;
+                    }
+                }
+            }
+            AppState::ItemMenu => {
+                match self
+                    .item_menu
+                    .as_mut()
+                    .unwrap()
+                    .tick_with_mouse(inputs, &view.item_view)
+                {
+                    None => (),
+                    Some(MenuOutput::Cancel) => {
+                        self.app_state = AppState::Game;
+                        self.item_menu = None;
+                    }
+                    Some(MenuOutput::Quit) => return Some(Tick::Quit),
+                    Some(MenuOutput::Finalise(&item)) => {
+                        let game_state = self.game_state.as_mut().unwrap();
+                        let input_start_index = game_state.all_inputs.len();
+                        game_state.all_inputs.push(gws::input::use_item(item));
+                        let tick = game_state.game.tick(
+                            game_state.all_inputs[input_start_index..].iter().cloned(),
+                            period,
+                            &mut game_state.rng_with_seed.rng,
+                        );
+                        if let Some(gws::Tick::CancelAction(_)) = tick {
+                            self.message = Some(format!(
+                                "The {} has no effect here.",
+                                item_text(item)
+                            ));
+                        }
+                        self.app_state = AppState::Game;
+                        self.item_menu = None;
+                    }
+                }
+            }
             AppState::FountainMenu => {
                 match self
                     .fountain_menu
@@ -774,9 +1319,13 @@ impl<F: Frontend, S: Storage> App<F, S> {
                         let input_start_index = game_state.all_inputs.len();
                         let interactive = self.interactive.unwrap();
                         let entity_id = interactive.entity_id;
-                        game_state.all_inputs.push(gws::input::interact(
-                            gws::InteractiveParam::Flame { entity_id, card },
-                        ));
+                        let param = match interactive.typ {
+                            gws::InteractiveType::Anvil => {
+                                gws::InteractiveParam::Anvil { entity_id, card }
+                            }
+                            _ => gws::InteractiveParam::Flame { entity_id, card },
+                        };
+                        game_state.all_inputs.push(gws::input::interact(param));
                         let input_end_index = game_state.all_inputs.len();
                         let _ = game_state.game.tick(
                             game_state.all_inputs[input_start_index..input_end_index]
@@ -799,6 +1348,7 @@ impl<F: Frontend, S: Storage> App<F, S> {
                         _other => {
                             self.app_state = AppState::Menu;
                             self.game_state = None;
+                            self.message = None;
                             self.delete_save();
                         }
                     }
@@ -824,7 +1374,7 @@ impl<F: Frontend, S: Storage> App<F, S> {
                                 return Some(Tick::Quit);
                             }
                             pause::Entry::NewGame => {
-                                self.app_state = AppState::BetweenLevels(None);
+                                self.app_state = AppState::NewGameMenu;
                             }
                             pause::Entry::Help => {
                                 self.app_state = AppState::Help {
@@ -848,7 +1398,7 @@ impl<F: Frontend, S: Storage> App<F, S> {
                         Some(MenuOutput::Finalise(selection)) => match selection {
                             main::Entry::Quit => return Some(Tick::Quit),
                             main::Entry::NewGame => {
-                                self.app_state = AppState::BetweenLevels(None);
+                                self.app_state = AppState::NewGameMenu;
                             }
                             main::Entry::Story => {
                                 self.app_state = AppState::Story;
@@ -862,6 +1412,57 @@ impl<F: Frontend, S: Storage> App<F, S> {
                     }
                 }
             }
+            AppState::NewGameMenu => {
+                match self
+                    .new_game_menu
+                    .tick_with_mouse(inputs, &view.new_game_view)
+                {
+                    None => (),
+                    Some(MenuOutput::Cancel) => self.app_state = AppState::Menu,
+                    Some(MenuOutput::Quit) => return Some(Tick::Quit),
+                    Some(MenuOutput::Finalise(&selection)) => match selection {
+                        new_game::Entry::Mode => {
+                            self.run_settings.mode = match self.run_settings.mode {
+                                gws::GameMode::Standard => gws::GameMode::Endless,
+                                gws::GameMode::Endless => gws::GameMode::Standard,
+                            };
+                        }
+                        new_game::Entry::Difficulty => {
+                            let index = gws::ALL_DIFFICULTIES
+                                .iter()
+                                .position(|&d| d == self.run_settings.difficulty)
+                                .unwrap();
+                            self.run_settings.difficulty = gws::ALL_DIFFICULTIES
+                                [(index + 1) % gws::ALL_DIFFICULTIES.len()];
+                        }
+                        new_game::Entry::Reshuffle => {
+                            let index = gws::ALL_RESHUFFLE_RULES
+                                .iter()
+                                .position(|&r| r == self.run_settings.reshuffle)
+                                .unwrap();
+                            self.run_settings.reshuffle = gws::ALL_RESHUFFLE_RULES
+                                [(index + 1) % gws::ALL_RESHUFFLE_RULES.len()];
+                        }
+                        new_game::Entry::Mutator(mutator) => {
+                            self.run_settings.toggle_mutator(mutator)
+                        }
+                        new_game::Entry::Start => {
+                            self.app_state = AppState::ClassMenu;
+                        }
+                    },
+                }
+            }
+            AppState::ClassMenu => {
+                match self.class_menu.tick_with_mouse(inputs, &view.class_view) {
+                    None => (),
+                    Some(MenuOutput::Cancel) => self.app_state = AppState::NewGameMenu,
+                    Some(MenuOutput::Quit) => return Some(Tick::Quit),
+                    Some(MenuOutput::Finalise(&player_class)) => {
+                        self.run_settings.player_class = player_class;
+                        self.app_state = AppState::BetweenLevels(None);
+                    }
+                }
+            }
             AppState::Game => {
                 if let Some(game_state) = self.game_state.as_mut() {
                     let input_start_index = game_state.all_inputs.len();
@@ -873,7 +1474,14 @@ impl<F: Frontend, S: Storage> App<F, S> {
                         if let Some(input) = inputs.into_iter().next() {
                             let slot = *slot; // TODO why is this necessary
                             let game_input = match input {
-                                Input::MouseMove { .. } => None,
+                                Input::MouseMove { coord, .. } => {
+                                    if let CardParamChoice::Coord(_) = *choice {
+                                        if let Some(coord) = screen_to_game_coord(coord) {
+                                            *choice = CardParamChoice::Coord(coord);
+                                        }
+                                    }
+                                    None
+                                }
                                 prototty_inputs::ESCAPE => {
                                     self.message = None;
                                     self.card_selection = None;
@@ -881,12 +1489,81 @@ impl<F: Frontend, S: Storage> App<F, S> {
                                 }
                                 prototty_inputs::ETX => return Some(Tick::Quit),
                                 ProtottyInput::Char(card_num @ '1'..='8') => {
-                                    let (message, card_selection) =
-                                        Self::select_card(game_state, card_num);
+                                    let (message, card_selection) = Self::select_card(
+                                        game_state,
+                                        card_num_to_index(card_num),
+                                    );
                                     self.message = message;
                                     self.card_selection = card_selection;
                                     None
                                 }
+                                Input::MousePress {
+                                    button: MouseButton::Left,
+                                    coord,
+                                } => {
+                                    if let Some(clicked_slot) = screen_to_card_slot(coord)
+                                    {
+                                        match *choice {
+                                            // clicking a selected card again plays it
+                                            CardParamChoice::Confirm
+                                                if clicked_slot == slot =>
+                                            {
+                                                Some(gws::input::play_card(
+                                                    slot,
+                                                    gws::CardParam::Confirm,
+                                                ))
+                                            }
+                                            _ => {
+                                                let (message, card_selection) =
+                                                    Self::select_card(
+                                                        game_state,
+                                                        clicked_slot,
+                                                    );
+                                                self.message = message;
+                                                self.card_selection = card_selection;
+                                                None
+                                            }
+                                        }
+                                    } else if let Some(coord) =
+                                        screen_to_game_coord(coord)
+                                    {
+                                        let player_coord =
+                                            game_state.game.to_render().player.coord();
+                                        match *choice {
+                                            CardParamChoice::Coord(_) => {
+                                                Some(gws::input::play_card(
+                                                    slot,
+                                                    gws::CardParam::Coord(coord),
+                                                ))
+                                            }
+                                            CardParamChoice::Direction => {
+                                                adjacent_direction(player_coord, coord)
+                                                    .map(|direction| {
+                                                        gws::input::play_card(
+                                                            slot,
+                                                            gws::CardParam::CardinalDirection(
+                                                                direction,
+                                                            ),
+                                                        )
+                                                    })
+                                            }
+                                            CardParamChoice::Confirm => None,
+                                        }
+                                    } else {
+                                        None
+                                    }
+                                }
+                                Input::MousePress {
+                                    button: MouseButton::Right,
+                                    ..
+                                } => {
+                                    self.message = None;
+                                    self.card_selection = None;
+                                    None
+                                }
+                                Input::MousePress { .. }
+                                | Input::MouseRelease { .. }
+                                | Input::MouseScroll { .. } => None,
                                 _ => match *choice {
                                     CardParamChoice::Confirm => match input {
                                         prototty_inputs::RETURN => {
@@ -981,9 +1658,131 @@ impl<F: Frontend, S: Storage> App<F, S> {
                                 game_state.all_inputs.push(game_input);
                             }
                         }
+                    } else if let Some(selected) = self.mulligan.as_mut() {
+                        if let Some(input) = inputs.into_iter().next() {
+                            let toggle_slot = match input {
+                                ProtottyInput::Char(card_num @ '1'..='8') => {
+                                    Some(card_num_to_index(card_num))
+                                }
+                                Input::MousePress {
+                                    button: MouseButton::Left,
+                                    coord,
+                                } => screen_to_card_slot(coord),
+                                _ => None,
+                            };
+                            match input {
+                                prototty_inputs::ETX => return Some(Tick::Quit),
+                                prototty_inputs::ESCAPE
+                                | Input::MousePress {
+                                    button: MouseButton::Right,
+                                    ..
+                                } => {
+                                    self.message = None;
+                                    self.mulligan = None;
+                                }
+                                prototty_inputs::RETURN => {
+                                    if selected.is_empty() {
+                                        self.message =
+                                            Some("No cards are selected.".to_string());
+                                    } else {
+                                        game_state
+                                            .all_inputs
+                                            .push(gws::input::mulligan(selected));
+                                        self.mulligan = None;
+                                    }
+                                }
+                                _ => {
+                                    let hand = game_state.game.hand();
+                                    if let Some(slot) = toggle_slot.filter(|&slot| {
+                                        hand.get(slot).is_some_and(Option::is_some)
+                                    }) {
+                                        if let Some(index) =
+                                            selected.iter().position(|&s| s == slot)
+                                        {
+                                            selected.remove(index);
+                                        } else {
+                                            selected.push(slot);
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    } else if self.closing_door {
+                        if let Some(input) = inputs.into_iter().next() {
+                            match input {
+                                Input::MouseMove { .. } => (),
+                                prototty_inputs::ETX => return Some(Tick::Quit),
+                                _ => {
+                                    self.closing_door = false;
+                                    self.message = None;
+                                    let direction = match input {
+                                        ProtottyInput::Up => {
+                                            Some(CardinalDirection::North)
+                                        }
+                                        ProtottyInput::Down => {
+                                            Some(CardinalDirection::South)
+                                        }
+                                        ProtottyInput::Left => {
+                                            Some(CardinalDirection::West)
+                                        }
+                                        ProtottyInput::Right => {
+                                            Some(CardinalDirection::East)
+                                        }
+                                        _ => None,
+                                    };
+                                    if let Some(direction) = direction {
+                                        game_state
+                                            .all_inputs
+                                            .push(gws::input::close_door(direction));
+                                    }
+                                }
+                            }
+                        }
                     } else {
                         for input in inputs {
+                            if !is_mouse_input(input) {
+                                self.activity = None;
+                                self.view_cursor = None;
+                            }
                             match input {
+                                Input::MouseMove { coord, .. } => {
+                                    if let Some(coord) = screen_to_game_coord(coord) {
+                                        self.view_cursor = Some(coord);
+                                        self.message = describe(&game_state.game, coord);
+                                    } else if self.view_cursor.take().is_some() {
+                                        self.message = None;
+                                    }
+                                }
+                                Input::MousePress {
+                                    button: MouseButton::Left,
+                                    coord,
+                                } => {
+                                    if let Some(slot) = screen_to_card_slot(coord) {
+                                        let (message, card_selection) =
+                                            Self::select_card(game_state, slot);
+                                        self.message = message;
+                                        self.card_selection = card_selection;
+                                    } else if let Some(coord) =
+                                        screen_to_game_coord(coord)
+                                    {
+                                        let player_coord =
+                                            game_state.game.to_render().player.coord();
+                                        if coord == player_coord {
+                                            game_state.all_inputs.push(gws::input::WAIT);
+                                        } else if let Some(direction) =
+                                            adjacent_direction(player_coord, coord)
+                                        {
+                                            game_state
+                                                .all_inputs
+                                                .push(gws::Input::Move(direction));
+                                        } else {
+                                            self.activity = Some(ActivityState::new(
+                                                Activity::Travel(coord),
+                                                &game_state.game,
+                                            ));
+                                        }
+                                    }
+                                }
                                 ProtottyInput::Up => {
                                     game_state.all_inputs.push(gws::input::UP)
                                 }
@@ -1018,14 +1817,81 @@ impl<F: Frontend, S: Storage> App<F, S> {
                                 ProtottyInput::Char('b') => {
                                     self.app_state = AppState::ListBurnt;
                                 }
+                                ProtottyInput::Char('o') => {
+                                    self.app_state = AppState::Overview;
+                                }
+                                ProtottyInput::Char('i') => {
+                                    let items = game_state.game.items();
+                                    if items.is_empty() {
+                                        self.message = Some(
+                                            "You aren't carrying any items.".to_string(),
+                                        );
+                                    } else {
+                                        self.item_menu =
+                                            MenuInstance::new(items.to_vec()).ok();
+                                        self.app_state = AppState::ItemMenu;
+                                    }
+                                }
+                                ProtottyInput::Char('c') => {
+                                    self.message = Some(
+                                        "Close a door in which direction?".to_string(),
+                                    );
+                                    self.closing_door = true;
+                                }
+                                ProtottyInput::Char('u') => {
+                                    if game_state.game.can_mulligan() {
+                                        self.message = Some(format!(
+                                            "Mulligan: Select cards to discard, then press Enter ({} power).",
+                                            gws::MULLIGAN_COST
+                                        ));
+                                        self.mulligan = Some(Vec::new());
+                                    } else {
+                                        self.message = Some(
+                                            "You have already taken a mulligan this hand."
+                                                .to_string(),
+                                        );
+                                    }
+                                }
+                                ProtottyInput::Char('.') => {
+                                    game_state.all_inputs.push(gws::input::WAIT)
+                                }
+                                ProtottyInput::Char('r') => {
+                                    self.activity = Some(ActivityState::new(
+                                        Activity::Rest,
+                                        &game_state.game,
+                                    ));
+                                }
+                                ProtottyInput::Char('x') => {
+                                    self.activity = Some(ActivityState::new(
+                                        Activity::Explore,
+                                        &game_state.game,
+                                    ));
+                                }
+                                ProtottyInput::Char('>') => {
+                                    if let Some(coord) =
+                                        game_state.game.discovered_exit_coord()
+                                    {
+                                        self.activity = Some(ActivityState::new(
+                                            Activity::Travel(coord),
+                                            &game_state.game,
+                                        ));
+                                    } else {
+                                        self.message = Some(
+                                            "You haven't found the stairs yet."
+                                                .to_string(),
+                                        );
+                                    }
+                                }
                                 ProtottyInput::Char('v') => {
                                     self.view_cursor =
                                         Some(game_state.game.to_render().player.coord());
                                     self.app_state = AppState::ViewCursor;
                                 }
                                 ProtottyInput::Char(card_num @ '1'..='8') => {
-                                    let (message, card_selection) =
-                                        Self::select_card(game_state, card_num);
+                                    let (message, card_selection) = Self::select_card(
+                                        game_state,
+                                        card_num_to_index(card_num),
+                                    );
                                     self.message = message;
                                     self.card_selection = card_selection;
                                 }
@@ -1037,11 +1903,26 @@ impl<F: Frontend, S: Storage> App<F, S> {
                             }
                         }
                     }
+                    if let Some(activity) = self.activity.as_mut() {
+                        if game_state.all_inputs.len() == input_start_index
+                            && game_state.game.is_awaiting_input()
+                        {
+                            match activity.step(&mut game_state.game) {
+                                Ok(input) => game_state.all_inputs.push(input),
+                                Err(message) => {
+                                    self.activity = None;
+                                    self.message = Some(message.to_string());
+                                }
+                            }
+                        }
+                    }
                     let input_end_index = game_state.all_inputs.len();
                     if input_end_index != input_start_index {
                         self.message = None;
                         self.card_selection = None;
                     }
+                    let reshuffles = game_state.game.reshuffles();
+                    let items_left_behind = game_state.game.items_left_behind();
                     let tick = game_state.game.tick(
                         game_state.all_inputs[input_start_index..input_end_index]
                             .into_iter()
@@ -1049,7 +1930,24 @@ impl<F: Frontend, S: Storage> App<F, S> {
                         period,
                         &mut game_state.rng_with_seed.rng,
                     );
+                    if game_state.game.reshuffles() > reshuffles {
+                        self.activity = None;
+                        self.message = Some(reshuffle_message(&game_state.game));
+                    }
+                    if game_state.game.items_left_behind() > items_left_behind {
+                        self.message = Some(format!(
+                            "You can't carry more than {} items!",
+                            gws::MAX_ITEMS
+                        ));
+                    }
+                    let player_coord = game_state.game.to_render().player.coord();
+                    if self.activity.as_ref().map(|state| state.activity)
+                        == Some(Activity::Travel(player_coord))
+                    {
+                        self.activity = None;
+                    }
                     if let Some(tick) = tick {
+                        self.activity = None;
                         match tick {
                             gws::Tick::Interact(interactive) => {
                                 self.interactive = Some(interactive);
@@ -1069,6 +1967,66 @@ impl<F: Frontend, S: Storage> App<F, S> {
                                             self.app_state = AppState::CardMenu;
                                         }
                                     }
+                                    gws::InteractiveType::Anvil => {
+                                        let cards = menus::card::upgradable(
+                                            game_state.game.deck(),
+                                        );
+                                        if cards.is_empty() {
+                                            self.message = Some("You must have upgradable cards in your deck to use the Smith's Anvil.".to_string());
+                                        } else {
+                                            self.card_menu_title =
+                                                "Smith's Anvil: Upgrade a card in your deck."
+                                                    .to_string();
+                                            self.card_menu =
+                                                MenuInstance::new(cards).ok();
+                                            self.app_state = AppState::CardMenu;
+                                        }
+                                    }
+                                    gws::InteractiveType::Merchant => {
+                                        let choices = menus::merchant::choices(
+                                            interactive.entity_id,
+                                            &game_state.game,
+                                        );
+                                        if choices.is_empty() {
+                                            self.message = Some(
+                                                "The merchant has nothing left to sell."
+                                                    .to_string(),
+                                            );
+                                        } else {
+                                            self.merchant_menu =
+                                                MenuInstance::new(choices).ok();
+                                            self.app_state = AppState::MerchantMenu;
+                                        }
+                                    }
+                                    gws::InteractiveType::Chest => {
+                                        let num_relics = game_state.game.relics().len();
+                                        let input_start_index =
+                                            game_state.all_inputs.len();
+                                        game_state.all_inputs.push(gws::input::interact(
+                                            gws::InteractiveParam::Chest {
+                                                entity_id: interactive.entity_id,
+                                            },
+                                        ));
+                                        let _ = game_state.game.tick(
+                                            game_state.all_inputs[input_start_index..]
+                                                .iter()
+                                                .cloned(),
+                                            period,
+                                            &mut game_state.rng_with_seed.rng,
+                                        );
+                                        self.interactive = None;
+                                        self.message = Some(
+                                            match game_state.game.relics().get(num_relics)
+                                            {
+                                                Some(&relic) => format!(
+                                                    "You found the {}: {}",
+                                                    relic_text(relic),
+                                                    relic_description(relic)
+                                                ),
+                                                None => "The chest is empty.".to_string(),
+                                            },
+                                        );
+                                    }
                                     gws::InteractiveType::Altar => {
                                         self.altar_menu = Some(
                                             MenuInstance::new(menus::altar::choices(
@@ -1092,13 +2050,44 @@ impl<F: Frontend, S: Storage> App<F, S> {
                                 }
                             }
                             gws::Tick::End(end) => match end {
-                                gws::End::ExitLevel(between_levels) => {
+                                gws::End::ExitLevel(mut between_levels) => {
+                                    game_state
+                                        .game
+                                        .take_stored_levels(&mut between_levels);
                                     self.app_state =
-                                        AppState::BetweenLevels(Some(between_levels));
+                                        AppState::BetweenLevels(Some(*between_levels));
                                     self.card_selection = None;
+                                    self.mulligan = None;
                                     self.message = None;
                                 }
                                 gws::End::PlayerDied => {
+                                    self.message = Some(match game_state.game.mode() {
+                                        gws::GameMode::Standard => {
+                                            "You died. Press any key...".to_string()
+                                        }
+                                        // score endless runs by the depth reached
+                                        gws::GameMode::Endless => {
+                                            let depth = game_state.game.dungeon_level();
+                                            let best_depth = self
+                                                .storage
+                                                .load::<_, u32>(BEST_DEPTH_KEY)
+                                                .unwrap_or(0)
+                                                .max(depth);
+                                            if let Err(error) = self
+                                                .storage
+                                                .store(BEST_DEPTH_KEY, &best_depth)
+                                            {
+                                                eprintln!(
+                                                    "Failed to save best depth: {:?}",
+                                                    error
+                                                );
+                                            }
+                                            format!(
+                                                "You died at depth {} (best {}). Press any key...",
+                                                depth, best_depth
+                                            )
+                                        }
+                                    });
                                     self.save();
                                     self.app_state = AppState::Death;
                                 }
@@ -1113,6 +2102,9 @@ impl<F: Frontend, S: Storage> App<F, S> {
                                         self.message =
                                             Some("Can't move there!".to_string())
                                     }
+                                    MoveIntoChasm => {
+                                        self.message = Some("You would fall!".to_string())
+                                    }
                                     LocationBlocked => {
                                         self.message =
                                             Some("Location is blocked".to_string())
@@ -1123,6 +2115,15 @@ impl<F: Frontend, S: Storage> App<F, S> {
                                     NothingToAttack => {
                                         self.message = Some("Nothing there!".to_string())
                                     }
+                                    NoDoor => {
+                                        self.message = Some(
+                                            "There's no open door there!".to_string(),
+                                        )
+                                    }
+                                    EndGuarded => {
+                                        self.message =
+                                            Some("The Guardian bars the way!".to_string())
+                                    }
                                     AlreadyFullHitPoints => {
                                         self.message =
                                             Some("Health is already full!".to_string())
@@ -1135,6 +2136,28 @@ impl<F: Frontend, S: Storage> App<F, S> {
                                         self.message =
                                             Some("Not enough power!".to_string())
                                     }
+                                    NotEnoughGold => {
+                                        self.message =
+                                            Some("Not enough gold!".to_string())
+                                    }
+                                    NoSuchItem => {
+                                        self.message =
+                                            Some("You don't have that item!".to_string())
+                                    }
+                                    HandFull => {
+                                        self.message = Some(
+                                            "Your hand can't grow any larger!"
+                                                .to_string(),
+                                        )
+                                    }
+                                    NoTeleportDestination => {
+                                        self.message =
+                                            Some("Nowhere to teleport to!".to_string())
+                                    }
+                                    MulliganUsed => self.message = Some(
+                                        "You have already taken a mulligan this hand."
+                                            .to_string(),
+                                    ),
                                     _ => (),
                                 }
                             }
@@ -1180,14 +2203,154 @@ impl<F: Frontend, S: Storage> App<F, S> {
                     }
                 }
             }
-            AppState::BetweenLevels(ref between_levels) => {
+            AppState::BonusMenu(_) => {
+                let character_upgrade = match self
+                    .bonus_menu
+                    .as_mut()
+                    .unwrap()
+                    .tick_with_mouse(inputs, &view.bonus_view)
+                {
+                    None | Some(MenuOutput::Cancel) => None,
+                    Some(MenuOutput::Quit) => return Some(Tick::Quit),
+                    Some(MenuOutput::Finalise(&character_upgrade)) => {
+                        Some(character_upgrade)
+                    }
+                };
+                if let Some(character_upgrade) = character_upgrade {
+                    if let AppState::BonusMenu(mut between_levels) =
+                        mem::replace(&mut self.app_state, AppState::Game)
+                    {
+                        between_levels.apply_bonus_upgrade(character_upgrade);
+                        self.app_state = AppState::BetweenLevels(Some(between_levels));
+                    }
+                    self.bonus_menu = None;
+                }
+            }
+            AppState::BetweenLevels(Some(ref between_levels))
+                if between_levels.bonus_upgrades() > 0 =>
+            {
+                let choices = between_levels
+                    .choose_bonus_upgrades(
+                        NUM_BONUS_UPGRADE_CHOICES,
+                        &mut self.rng_source.rng,
+                    )
+                    .cloned()
+                    .collect();
+                self.bonus_menu = Some(MenuInstance::new(choices).unwrap());
+                if let AppState::BetweenLevels(Some(between_levels)) =
+                    mem::replace(&mut self.app_state, AppState::Game)
+                {
+                    self.app_state = AppState::BonusMenu(between_levels);
+                }
+            }
+            AppState::BetweenLevels(Some(ref between_levels))
+                if between_levels.reward_pending() =>
+            {
+                let cards = between_levels.choose_reward_cards(
+                    NUM_REWARD_CARD_CHOICES,
+                    &mut self.rng_source.rng,
+                );
+                self.reward_menu = Some(
+                    MenuInstance::new(reward::choices(&cards, between_levels)).unwrap(),
+                );
+                if let AppState::BetweenLevels(Some(between_levels)) =
+                    mem::replace(&mut self.app_state, AppState::Game)
+                {
+                    self.app_state = AppState::RewardMenu(between_levels);
+                }
+            }
+            AppState::RewardMenu(_) => {
+                let choice = match self
+                    .reward_menu
+                    .as_mut()
+                    .unwrap()
+                    .tick_with_mouse(inputs, &view.reward_view)
+                {
+                    None | Some(MenuOutput::Cancel) => None,
+                    Some(MenuOutput::Quit) => return Some(Tick::Quit),
+                    Some(MenuOutput::Finalise(&choice)) => Some(choice),
+                };
+                if let Some(choice) = choice {
+                    if let AppState::RewardMenu(mut between_levels) =
+                        mem::replace(&mut self.app_state, AppState::Game)
+                    {
+                        let reward = match choice {
+                            reward::Entry::Take(card) => Some(gws::Reward::Take(card)),
+                            reward::Entry::Skip => Some(gws::Reward::Skip),
+                            reward::Entry::Remove | reward::Entry::Upgrade => None,
+                        };
+                        if let Some(reward) = reward {
+                            between_levels.apply_reward(reward);
+                            self.app_state =
+                                AppState::BetweenLevels(Some(between_levels));
+                            self.reward_menu = None;
+                        } else {
+                            let cards = match choice {
+                                reward::Entry::Upgrade => {
+                                    reward::upgradable_cards(&between_levels)
+                                }
+                                _ => reward::removable_cards(&between_levels),
+                            };
+                            self.reward_card_menu =
+                                Some(MenuInstance::new(cards).unwrap());
+                            self.app_state =
+                                AppState::RewardCardMenu(between_levels, choice);
+                        }
+                    }
+                }
+            }
+            AppState::RewardCardMenu(..) => {
+                let card_output = match self
+                    .reward_card_menu
+                    .as_mut()
+                    .unwrap()
+                    .tick_with_mouse(inputs, &view.flame_view)
+                {
+                    None => None,
+                    Some(MenuOutput::Quit) => return Some(Tick::Quit),
+                    Some(MenuOutput::Cancel) => Some(None),
+                    Some(MenuOutput::Finalise(&card)) => Some(Some(card)),
+                };
+                if let Some(card) = card_output {
+                    if let AppState::RewardCardMenu(mut between_levels, choice) =
+                        mem::replace(&mut self.app_state, AppState::Game)
+                    {
+                        self.reward_card_menu = None;
+                        match card {
+                            // back to the choice of reward
+                            None => self.app_state = AppState::RewardMenu(between_levels),
+                            Some(card) => {
+                                between_levels.apply_reward(match choice {
+                                    reward::Entry::Upgrade => gws::Reward::Upgrade(card),
+                                    _ => gws::Reward::Remove(card),
+                                });
+                                self.app_state =
+                                    AppState::BetweenLevels(Some(between_levels));
+                                self.reward_menu = None;
+                            }
+                        }
+                    }
+                }
+            }
+            AppState::BetweenLevels(_) => {
+                let between_levels = if let AppState::BetweenLevels(between_levels) =
+                    mem::replace(&mut self.app_state, AppState::Game)
+                {
+                    between_levels
+                } else {
+                    None
+                };
                 let rng_with_seed = self.rng_source.next();
                 let seed = rng_with_seed.seed;
                 let first_level = between_levels.is_none();
+                let run_settings = &self.run_settings;
                 self.game_state = Some(GameState::new(
-                    between_levels.clone(),
+                    between_levels.unwrap_or_else(|| {
+                        gws::BetweenLevels::new_game(run_settings.clone())
+                    }),
                     rng_with_seed,
                     self.debug_terrain_string.as_ref().map(String::as_str),
+                    self.wfc_sample.as_ref(),
                 ));
                 self.app_state = AppState::Game;
                 if first_level {
@@ -1208,39 +2371,53 @@ impl<F: Frontend, S: Storage> App<F, S> {
     }
     fn select_card(
         game_state: &GameState,
-        card_num: char,
+        card_index: usize,
     ) -> (Option<String>, Option<CardInSlot>) {
-        let card_index = card_num.to_digit(10).unwrap() as usize - 1;
         let hand = game_state.game.hand();
         let message;
         let card_selection;
         if let Some(&maybe_card) = hand.get(card_index) {
             if let Some(card) = maybe_card {
-                if game_state.game.draw_countdown().current < card.cost() {
+                if game_state.game.draw_countdown().current
+                    < game_state.game.card_cost(card)
+                {
                     message = Some("Not enough power!".to_string());
                     card_selection = None;
                 } else {
                     let choice = match card {
                         gws::Card::Bump
+                        | gws::Card::BumpPlus
                         | gws::Card::Spark
+                        | gws::Card::SparkPlus
                         | gws::Card::Bash
+                        | gws::Card::Smash
                         | gws::Card::Deposit
-                        | gws::Card::Caltrop => {
+                        | gws::Card::DepositPlus
+                        | gws::Card::Caltrop
+                        | gws::Card::CaltropPlus => {
                             message = Some("Choose a direction.".to_string());
                             CardParamChoice::Direction
                         }
                         gws::Card::Blink
+                        | gws::Card::BlinkPlus
+                        | gws::Card::Flicker
                         | gws::Card::Block
+                        | gws::Card::BlockPlus
                         | gws::Card::Surround
+                        | gws::Card::SurroundPlus
                         | gws::Card::Freeze
+                        | gws::Card::FreezePlus
                         | gws::Card::Shred
-                        | gws::Card::Spike => {
+                        | gws::Card::ShredPlus
+                        | gws::Card::Spike
+                        | gws::Card::SpikePlus => {
                             message = Some("Choose a location.".to_string());
                             CardParamChoice::Coord(
                                 game_state.game.to_render().player.coord(),
                             )
                         }
                         gws::Card::Heal
+                        | gws::Card::HealPlus
                         | gws::Card::Blast
                         | gws::Card::Save
                         | gws::Card::Spend
@@ -1250,6 +2427,8 @@ impl<F: Frontend, S: Storage> App<F, S> {
                         | gws::Card::Parasite
                         | gws::Card::Garden
                         | gws::Card::Armour
+                        | gws::Card::Rampart
+                        | gws::Card::Poise
                         | gws::Card::Empower
                         | gws::Card::Drain => {
                             message = Some("Confirm selection.".to_string());
@@ -1262,11 +2441,11 @@ impl<F: Frontend, S: Storage> App<F, S> {
                     });
                 }
             } else {
-                message = Some(format!("No card in slot {}.", card_num));
+                message = Some(format!("No card in slot {}.", card_index + 1));
                 card_selection = None;
             }
         } else {
-            message = Some(format!("Card slot {} is locked.", card_num));
+            message = Some(format!("Card slot {} is locked.", card_index + 1));
             card_selection = None;
         }
         (message, card_selection)
@@ -1278,9 +2457,15 @@ impl AppView {
         Self {
             menu_and_title_view: MenuInstanceView::new(main::EntryView),
             pause_menu_and_title_view: MenuInstanceView::new(pause::EntryView),
+            new_game_view: MenuInstanceView::new(new_game::EntryView),
+            class_view: MenuInstanceView::new(class::EntryView),
             flame_view: MenuInstanceView::new(card::EntryView),
             altar_view: MenuInstanceView::new(altar::EntryView),
             fountain_view: MenuInstanceView::new(fountain::EntryView),
+            item_view: MenuInstanceView::new(item::EntryView),
+            merchant_view: MenuInstanceView::new(merchant::EntryView),
+            bonus_view: MenuInstanceView::new(bonus::EntryView),
+            reward_view: MenuInstanceView::new(reward::EntryView),
             string_view_word_wrap: StringView::new_default_style(wrap::Word::new()),
         }
     }
@@ -1,3 +1,4 @@
+use crate::game_view::branch_colour;
 use gws::*;
 use prototty::*;
 
@@ -11,6 +12,26 @@ const WALL: ViewCell = ViewCell::new()
     .with_character('#')
     .with_foreground(colours::WHITE)
     .with_background(colours::BLACK);
+const WATER: ViewCell = ViewCell::new()
+    .with_character('~')
+    .with_foreground(rgb24(80, 160, 255))
+    .with_background(colours::BLACK);
+const LAVA: ViewCell = ViewCell::new()
+    .with_character('~')
+    .with_foreground(rgb24(255, 80, 0))
+    .with_background(colours::BLACK);
+const CHASM: ViewCell = ViewCell::new()
+    .with_character(':')
+    .with_foreground(rgb24(100, 100, 100))
+    .with_background(colours::BLACK);
+const DOOR: ViewCell = ViewCell::new()
+    .with_character('+')
+    .with_foreground(rgb24(220, 160, 60))
+    .with_background(colours::BLACK);
+const CRACKED_WALL: ViewCell = ViewCell::new()
+    .with_character('%')
+    .with_foreground(colours::WHITE)
+    .with_background(colours::BLACK);
 const PLAYER: ViewCell = ViewCell::new()
     .with_character('@')
     .with_bold(true)
@@ -21,12 +42,23 @@ const TREE: ViewCell = ViewCell::new()
     .with_bold(true)
     .with_foreground(colours::WHITE)
     .with_background(colours::BLACK);
+const UP_STAIRS: ViewCell = ViewCell::new()
+    .with_character('<')
+    .with_bold(true)
+    .with_foreground(rgb24(220, 220, 120))
+    .with_background(colours::BLACK);
 const STAIRS: ViewCell = ViewCell::new()
     .with_character('>')
     .with_bold(true)
     .with_foreground(rgb24(220, 100, 50))
     .with_background(colours::BLACK);
 
+const KEY: ViewCell = ViewCell::new()
+    .with_character('♀')
+    .with_bold(true)
+    .with_foreground(rgb24(255, 210, 0))
+    .with_background(colours::BLACK);
+
 const FLAME: ViewCell = ViewCell::new()
     .with_character('Ψ')
     .with_bold(true)
@@ -45,6 +77,54 @@ const FOUNTAIN: ViewCell = ViewCell::new()
     .with_foreground(rgb24(50, 100, 200))
     .with_background(colours::BLACK);
 
+const ANVIL: ViewCell = ViewCell::new()
+    .with_character('π')
+    .with_bold(true)
+    .with_foreground(rgb24(200, 180, 120))
+    .with_background(colours::BLACK);
+
+const CHEST: ViewCell = ViewCell::new()
+    .with_character('¤')
+    .with_bold(true)
+    .with_foreground(rgb24(220, 170, 50))
+    .with_background(colours::BLACK);
+
+const BOMB: ViewCell = ViewCell::new()
+    .with_character('●')
+    .with_bold(true)
+    .with_foreground(rgb24(200, 60, 40))
+    .with_background(colours::BLACK);
+
+const MAPPING_SCROLL: ViewCell = ViewCell::new()
+    .with_character('§')
+    .with_bold(true)
+    .with_foreground(rgb24(220, 210, 160))
+    .with_background(colours::BLACK);
+
+const TORCH: ViewCell = ViewCell::new()
+    .with_character('¡')
+    .with_bold(true)
+    .with_foreground(rgb24(255, 180, 80))
+    .with_background(colours::BLACK);
+
+const TELEPORT_CHARM: ViewCell = ViewCell::new()
+    .with_character('♦')
+    .with_bold(true)
+    .with_foreground(rgb24(160, 80, 255))
+    .with_background(colours::BLACK);
+
+const GOLD: ViewCell = ViewCell::new()
+    .with_character('$')
+    .with_bold(true)
+    .with_foreground(rgb24(255, 215, 0))
+    .with_background(colours::BLACK);
+
+const MERCHANT: ViewCell = ViewCell::new()
+    .with_character('¥')
+    .with_bold(true)
+    .with_foreground(rgb24(200, 200, 80))
+    .with_background(colours::BLACK);
+
 impl<'a> View<&'a Gws> for MapView {
     fn view<G: ViewGrid, R: ViewTransformRgb24>(
         &mut self,
@@ -63,16 +143,25 @@ impl<'a> View<&'a Gws> for MapView {
                 continue;
             }
             let mut view_cell = match cell.background_tile() {
-                BackgroundTile::Floor | BackgroundTile::Ground => FLOOR,
+                BackgroundTile::Floor
+                | BackgroundTile::Ground
+                | BackgroundTile::DoorOpen
+                | BackgroundTile::Rubble => FLOOR,
+                BackgroundTile::DoorClosed => DOOR,
+                BackgroundTile::CrackedWall => CRACKED_WALL,
                 BackgroundTile::IceWall
                 | BackgroundTile::StoneWall
                 | BackgroundTile::BrickWall => WALL,
+                BackgroundTile::Water => WATER,
+                BackgroundTile::Lava => LAVA,
+                BackgroundTile::Chasm => CHASM,
             };
             for entity in cell.entity_iter(to_render.world.entities()) {
                 let foreground_view_cell =
                     entity.foreground_tile().and_then(|foreground_tile| {
                         match foreground_tile {
                             ForegroundTile::Bruiser => None,
+                            ForegroundTile::Boss => None,
                             ForegroundTile::Block => None,
                             ForegroundTile::Spike => None,
                             ForegroundTile::HealthPickup => None,
@@ -85,10 +174,22 @@ impl<'a> View<&'a Gws> for MapView {
                             ForegroundTile::Blink1 => None,
                             ForegroundTile::Player => Some(PLAYER),
                             ForegroundTile::Tree => Some(TREE),
-                            ForegroundTile::Stairs => Some(STAIRS),
+                            ForegroundTile::Stairs => Some(STAIRS.with_foreground(
+                                branch_colour(entity.branch().unwrap_or(Branch::Main)),
+                            )),
+                            ForegroundTile::UpStairs => Some(UP_STAIRS),
                             ForegroundTile::Flame => Some(FLAME),
+                            ForegroundTile::Key => Some(KEY),
                             ForegroundTile::Altar => Some(ALTAR),
                             ForegroundTile::Fountain => Some(FOUNTAIN),
+                            ForegroundTile::Anvil => Some(ANVIL),
+                            ForegroundTile::Chest => Some(CHEST),
+                            ForegroundTile::Bomb => Some(BOMB),
+                            ForegroundTile::MappingScroll => Some(MAPPING_SCROLL),
+                            ForegroundTile::Torch => Some(TORCH),
+                            ForegroundTile::TeleportCharm => Some(TELEPORT_CHARM),
+                            ForegroundTile::Gold => Some(GOLD),
+                            ForegroundTile::Merchant => Some(MERCHANT),
                         }
                     });
                 if let Some(foreground_view_cell) = foreground_view_cell {
@@ -5,6 +5,111 @@ use prototty::*;
 const NORMAL_COLOUR: Rgb24 = Rgb24::new(100, 100, 150);
 const SELECTED_COLOUR: Rgb24 = Rgb24::new(0, 120, 240);
 
+pub fn upgrade_text(upgrade: CharacterUpgrade) -> &'static str {
+    use CharacterUpgrade::*;
+    match upgrade {
+        Life => "Increase Max Life",
+        Hand => "Increase Hand Size",
+        Power => "Increase Max Power",
+        Vision => "Increase Vision",
+    }
+}
+
+pub fn relic_text(relic: Relic) -> &'static str {
+    match relic {
+        Relic::Hourglass => "Hourglass",
+        Relic::Whetstone => "Whetstone",
+        Relic::Bandage => "Bandage",
+        Relic::Lodestone => "Lodestone",
+    }
+}
+
+pub fn relic_description(relic: Relic) -> &'static str {
+    match relic {
+        Relic::Hourglass => "The first card played from each hand costs 0.",
+        Relic::Whetstone => "Spikes deal 2 damage to enemies.",
+        Relic::Bandage => "Heal 1 hit point when leaving a level.",
+        Relic::Lodestone => "Blink range is increased by 4.",
+    }
+}
+
+pub fn branch_text(branch: Branch) -> &'static str {
+    match branch {
+        Branch::Main => "Main Path",
+        Branch::Ice => "Ice Path",
+        Branch::Ruins => "Ruins Path",
+    }
+}
+
+pub fn branch_description(branch: Branch) -> &'static str {
+    match branch {
+        Branch::Main => "The way down.",
+        Branch::Ice => "Fewer enemies, and more health potions.",
+        Branch::Ruins => "More enemies and elites, guarding more treasure.",
+    }
+}
+
+pub fn item_text(item: Item) -> &'static str {
+    match item {
+        Item::Bomb => "Bomb",
+        Item::MappingScroll => "Scroll of Mapping",
+        Item::Torch => "Torch",
+        Item::TeleportCharm => "Teleport Charm",
+    }
+}
+
+pub fn item_description(item: Item) -> &'static str {
+    match item {
+        Item::Bomb => "Deal 2 damage to every adjacent enemy.",
+        Item::MappingScroll => "Reveal the whole level on the map.",
+        Item::Torch => "Leave a bright light where you stand.",
+        Item::TeleportCharm => "Teleport somewhere you have explored, out of sight.",
+    }
+}
+
+pub fn player_class_text(player_class: PlayerClass) -> &'static str {
+    match player_class {
+        PlayerClass::Wanderer => "Wanderer",
+        PlayerClass::Warrior => "Warrior",
+        PlayerClass::Trapper => "Trapper",
+        PlayerClass::Mage => "Mage",
+    }
+}
+
+pub fn mode_text(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::Standard => "Standard",
+        GameMode::Endless => "Endless",
+    }
+}
+
+pub fn difficulty_text(difficulty: Difficulty) -> &'static str {
+    match difficulty {
+        Difficulty::Easy => "Easy",
+        Difficulty::Normal => "Normal",
+        Difficulty::Hard => "Hard",
+    }
+}
+
+pub fn reshuffle_text(reshuffle: ReshuffleRule) -> &'static str {
+    match reshuffle {
+        ReshuffleRule::Never => "Never",
+        ReshuffleRule::Waste => "Waste",
+        ReshuffleRule::SpentAndWaste => "Spent and Waste",
+        ReshuffleRule::Fatigue => "Fatigue",
+    }
+}
+
+pub fn mutator_text(mutator: Mutator) -> &'static str {
+    match mutator {
+        Mutator::Curse => "Start with a Curse",
+        Mutator::ToughEnemies => "Enemies +1 Life",
+        Mutator::NoHealthPickups => "No Health Potions",
+        Mutator::DoublePower => "Double Power",
+        Mutator::Permadark => "Permadark",
+    }
+}
+
 const SELECTED_VIEW: StringViewSingleLine =
     StringViewSingleLine::new(Style::new().with_foreground(SELECTED_COLOUR));
 const NORMAL_VIEW: StringViewSingleLine =
@@ -129,6 +234,158 @@ pub mod pause {
     }
 }
 
+pub mod new_game {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug)]
+    pub enum Entry {
+        Mode,
+        Difficulty,
+        Reshuffle,
+        Mutator(Mutator),
+        Start,
+    }
+
+    pub fn choices() -> Vec<Entry> {
+        let mut choices = vec![
+            Entry::Start,
+            Entry::Mode,
+            Entry::Difficulty,
+            Entry::Reshuffle,
+        ];
+        choices.extend(ALL_MUTATORS.iter().cloned().map(Entry::Mutator));
+        choices
+    }
+
+    fn text(choice: Entry, settings: &RunSettings) -> String {
+        match choice {
+            Entry::Mode => format!("Mode: {}", mode_text(settings.mode)),
+            Entry::Difficulty => {
+                format!("Difficulty: {}", difficulty_text(settings.difficulty))
+            }
+            Entry::Reshuffle => {
+                format!("Reshuffle: {}", reshuffle_text(settings.reshuffle))
+            }
+            Entry::Mutator(mutator) => {
+                let check = if settings.has_mutator(mutator) {
+                    'x'
+                } else {
+                    ' '
+                };
+                format!("[{}] {}", check, mutator_text(mutator))
+            }
+            Entry::Start => "Start".to_string(),
+        }
+    }
+
+    pub fn description(choice: Entry, settings: &RunSettings) -> &'static str {
+        match choice {
+            Entry::Mode => match settings.mode {
+                GameMode::Standard => "Find the cure at the bottom of the sixth level.",
+                GameMode::Endless => "Descend as deep as you can. The cure is optional.",
+            },
+            Entry::Difficulty => match settings.difficulty {
+                Difficulty::Easy => "Fewer enemies and elites, and 2 extra life.",
+                Difficulty::Normal => "The intended challenge.",
+                Difficulty::Hard => "More enemies and elites, and fewer potions.",
+            },
+            Entry::Reshuffle => match settings.reshuffle {
+                ReshuffleRule::Never => "An empty deck stays empty until the next level.",
+                ReshuffleRule::Waste => "An empty deck is refilled from the waste pile.",
+                ReshuffleRule::SpentAndWaste => {
+                    "An empty deck is refilled from the spent and waste piles."
+                }
+                ReshuffleRule::Fatigue => {
+                    "As Spent and Waste, but each refill on a level hurts more."
+                }
+            },
+            Entry::Mutator(mutator) => match mutator {
+                Mutator::Curse => "Your deck starts with a Clog.",
+                Mutator::ToughEnemies => "Every enemy has an extra hit point.",
+                Mutator::NoHealthPickups => "No health potions are found in the dungeon.",
+                Mutator::DoublePower => "Start with twice the maximum power.",
+                Mutator::Permadark => "Nothing gives off light but you.",
+            },
+            Entry::Start => "Choose a class and begin the search.",
+        }
+    }
+
+    pub struct EntryView;
+
+    impl MenuEntryLookupView<Entry, RunSettings> for EntryView {
+        fn normal<G: ViewGrid, R: ViewTransformRgb24>(
+            &mut self,
+            &choice: &Entry,
+            settings: &RunSettings,
+            context: ViewContext<R>,
+            grid: &mut G,
+        ) -> u32 {
+            let string = format!("  {}", text(choice, settings));
+            StringViewSingleLine::new(Style::new().with_foreground(NORMAL_COLOUR))
+                .view_reporting_intended_size(&string, context, grid)
+                .width()
+        }
+        fn selected<G: ViewGrid, R: ViewTransformRgb24>(
+            &mut self,
+            &choice: &Entry,
+            settings: &RunSettings,
+            context: ViewContext<R>,
+            grid: &mut G,
+        ) -> u32 {
+            let string = format!("> {}", text(choice, settings));
+            StringViewSingleLine::new(Style::new().with_foreground(SELECTED_COLOUR))
+                .view_reporting_intended_size(&string, context, grid)
+                .width()
+        }
+    }
+}
+
+pub mod class {
+    use super::*;
+
+    pub type Entry = PlayerClass;
+
+    pub fn choices() -> Vec<Entry> {
+        ALL_PLAYER_CLASSES.to_vec()
+    }
+
+    pub fn description(player_class: PlayerClass) -> &'static str {
+        match player_class {
+            PlayerClass::Wanderer => "A little of everything.",
+            PlayerClass::Warrior => "Tough, and strikes hard up close, but sees little.",
+            PlayerClass::Trapper => "Holds a large hand of spikes, blocks and caltrops.",
+            PlayerClass::Mage => "Fragile, but strikes from afar and sees far.",
+        }
+    }
+
+    pub struct EntryView;
+
+    impl MenuEntryView<Entry> for EntryView {
+        fn normal<G: ViewGrid, R: ViewTransformRgb24>(
+            &mut self,
+            &player_class: &Entry,
+            context: ViewContext<R>,
+            grid: &mut G,
+        ) -> u32 {
+            let text = format!("  {}", player_class_text(player_class));
+            StringViewSingleLine::new(Style::new().with_foreground(NORMAL_COLOUR))
+                .view_reporting_intended_size(&text, context, grid)
+                .width()
+        }
+        fn selected<G: ViewGrid, R: ViewTransformRgb24>(
+            &mut self,
+            &player_class: &Entry,
+            context: ViewContext<R>,
+            grid: &mut G,
+        ) -> u32 {
+            let text = format!("> {}", player_class_text(player_class));
+            StringViewSingleLine::new(Style::new().with_foreground(SELECTED_COLOUR))
+                .view_reporting_intended_size(&text, context, grid)
+                .width()
+        }
+    }
+}
+
 pub mod card {
     use super::*;
 
@@ -138,6 +395,14 @@ pub mod card {
         cards
     }
 
+    /// One entry per distinct card that has a next tier.
+    pub fn upgradable(cards: &[Card]) -> Vec<Card> {
+        let mut cards = create(cards);
+        cards.dedup();
+        cards.retain(|card| card.upgraded().is_some());
+        cards
+    }
+
     pub struct EntryView;
 
     impl MenuEntryLookupView<Card, CardTable> for EntryView {
@@ -168,6 +433,78 @@ pub mod card {
     }
 }
 
+pub mod reward {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug)]
+    pub enum Entry {
+        Take(Card),
+        Remove,
+        Upgrade,
+        Skip,
+    }
+
+    pub fn choices(cards: &[Card], between_levels: &BetweenLevels) -> Vec<Entry> {
+        let mut choices = cards.iter().cloned().map(Entry::Take).collect::<Vec<_>>();
+        if between_levels.can_remove_card() {
+            choices.push(Entry::Remove);
+        }
+        if !upgradable_cards(between_levels).is_empty() {
+            choices.push(Entry::Upgrade);
+        }
+        choices.push(Entry::Skip);
+        choices
+    }
+
+    pub fn removable_cards(between_levels: &BetweenLevels) -> Vec<Card> {
+        let mut cards = card::create(between_levels.deck());
+        cards.dedup();
+        cards
+    }
+
+    pub fn upgradable_cards(between_levels: &BetweenLevels) -> Vec<Card> {
+        card::upgradable(between_levels.deck())
+    }
+
+    fn text(choice: Entry, card_table: &CardTable) -> String {
+        match choice {
+            Entry::Take(card) => format!("Take {}", card_table.get(card).to_string()),
+            Entry::Remove => "Remove a card from your deck".to_string(),
+            Entry::Upgrade => "Upgrade a card in your deck".to_string(),
+            Entry::Skip => "Skip".to_string(),
+        }
+    }
+
+    pub struct EntryView;
+
+    impl MenuEntryLookupView<Entry, CardTable> for EntryView {
+        fn normal<G: ViewGrid, R: ViewTransformRgb24>(
+            &mut self,
+            &choice: &Entry,
+            card_table: &CardTable,
+            context: ViewContext<R>,
+            grid: &mut G,
+        ) -> u32 {
+            let string = format!("  {}", text(choice, card_table));
+            StringViewSingleLine::new(Style::new().with_foreground(NORMAL_COLOUR))
+                .view_reporting_intended_size(&string, context, grid)
+                .width()
+        }
+        fn selected<G: ViewGrid, R: ViewTransformRgb24>(
+            &mut self,
+            &choice: &Entry,
+            card_table: &CardTable,
+            context: ViewContext<R>,
+            grid: &mut G,
+        ) -> u32 {
+            let string = format!("> {}", text(choice, card_table));
+            StringViewSingleLine::new(Style::new().with_foreground(SELECTED_COLOUR))
+                .view_reporting_intended_size(&string, context, grid)
+                .width()
+        }
+    }
+}
+
 pub mod altar {
     use super::*;
 
@@ -189,16 +526,6 @@ pub mod altar {
         choices.collect()
     }
 
-    fn upgrade_text(upgrade: CharacterUpgrade) -> &'static str {
-        use CharacterUpgrade::*;
-        match upgrade {
-            Life => "Increase Max Life",
-            Hand => "Increase Hand Size",
-            Power => "Increase Max Power",
-            Vision => "Increase Vision",
-        }
-    }
-
     pub struct EntryView;
 
     impl MenuEntryLookupView<Entry, CardTable> for EntryView {
@@ -288,3 +615,152 @@ pub mod fountain {
         }
     }
 }
+
+pub mod merchant {
+    use super::*;
+
+    pub type Entry = Purchase;
+
+    pub fn choices(id: EntityId, game: &Gws) -> Vec<Entry> {
+        let stock = game
+            .to_render()
+            .world
+            .entities()
+            .get(&id)
+            .unwrap()
+            .upgrade()
+            .unwrap();
+        let mut removable = card::create(game.deck());
+        removable.dedup();
+        stock
+            .positive_cards
+            .iter()
+            .cloned()
+            .map(Purchase::Card)
+            .chain(
+                stock
+                    .character_upgrades
+                    .iter()
+                    .cloned()
+                    .filter(|&character_upgrade| {
+                        character_upgrade != CharacterUpgrade::Hand
+                            || game.can_upgrade_hand()
+                    })
+                    .map(Purchase::CharacterUpgrade),
+            )
+            .chain(removable.into_iter().map(Purchase::RemoveCard))
+            .collect()
+    }
+
+    fn text(purchase: Purchase, card_table: &CardTable) -> String {
+        let description = match purchase {
+            Purchase::Card(card) => format!("Buy {}", card_table.get(card).to_string()),
+            Purchase::RemoveCard(card) => {
+                format!("Remove {} from your deck", card_table.get(card).title)
+            }
+            Purchase::CharacterUpgrade(character_upgrade) => {
+                upgrade_text(character_upgrade).to_string()
+            }
+        };
+        format!("{} gold: {}", purchase.price(), description)
+    }
+
+    pub struct EntryView;
+
+    impl MenuEntryLookupView<Entry, CardTable> for EntryView {
+        fn normal<G: ViewGrid, R: ViewTransformRgb24>(
+            &mut self,
+            &purchase: &Entry,
+            card_table: &CardTable,
+            context: ViewContext<R>,
+            grid: &mut G,
+        ) -> u32 {
+            let text = format!("  {}", text(purchase, card_table));
+            StringViewSingleLine::new(Style::new().with_foreground(NORMAL_COLOUR))
+                .view_reporting_intended_size(&text, context, grid)
+                .width()
+        }
+        fn selected<G: ViewGrid, R: ViewTransformRgb24>(
+            &mut self,
+            &purchase: &Entry,
+            card_table: &CardTable,
+            context: ViewContext<R>,
+            grid: &mut G,
+        ) -> u32 {
+            let text = format!("> {}", text(purchase, card_table));
+            StringViewSingleLine::new(Style::new().with_foreground(SELECTED_COLOUR))
+                .view_reporting_intended_size(&text, context, grid)
+                .width()
+        }
+    }
+}
+
+pub mod item {
+    use super::*;
+
+    pub type Entry = Item;
+
+    fn text(item: Item) -> String {
+        format!("{}: {}", item_text(item), item_description(item))
+    }
+
+    pub struct EntryView;
+
+    impl MenuEntryView<Entry> for EntryView {
+        fn normal<G: ViewGrid, R: ViewTransformRgb24>(
+            &mut self,
+            &item: &Entry,
+            context: ViewContext<R>,
+            grid: &mut G,
+        ) -> u32 {
+            let text = format!("  {}", text(item));
+            StringViewSingleLine::new(Style::new().with_foreground(NORMAL_COLOUR))
+                .view_reporting_intended_size(&text, context, grid)
+                .width()
+        }
+        fn selected<G: ViewGrid, R: ViewTransformRgb24>(
+            &mut self,
+            &item: &Entry,
+            context: ViewContext<R>,
+            grid: &mut G,
+        ) -> u32 {
+            let text = format!("> {}", text(item));
+            StringViewSingleLine::new(Style::new().with_foreground(SELECTED_COLOUR))
+                .view_reporting_intended_size(&text, context, grid)
+                .width()
+        }
+    }
+}
+
+pub mod bonus {
+    use super::*;
+
+    pub type Entry = CharacterUpgrade;
+
+    pub struct EntryView;
+
+    impl MenuEntryView<Entry> for EntryView {
+        fn normal<G: ViewGrid, R: ViewTransformRgb24>(
+            &mut self,
+            &character_upgrade: &Entry,
+            context: ViewContext<R>,
+            grid: &mut G,
+        ) -> u32 {
+            let text = format!("  {}", upgrade_text(character_upgrade));
+            StringViewSingleLine::new(Style::new().with_foreground(NORMAL_COLOUR))
+                .view_reporting_intended_size(&text, context, grid)
+                .width()
+        }
+        fn selected<G: ViewGrid, R: ViewTransformRgb24>(
+            &mut self,
+            &character_upgrade: &Entry,
+            context: ViewContext<R>,
+            grid: &mut G,
+        ) -> u32 {
+            let text = format!("> {}", upgrade_text(character_upgrade));
+            StringViewSingleLine::new(Style::new().with_foreground(SELECTED_COLOUR))
+                .view_reporting_intended_size(&text, context, grid)
+                .width()
+        }
+    }
+}
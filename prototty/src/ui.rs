@@ -29,9 +29,42 @@ const CARDS_OFFSET: Coord = Coord {
 };
 const CARD_SIZE: Coord = Coord::new(8, 10);
 const CARD_PADDING_X: i32 = 1;
+/// Where a slot's card is drawn, below the slot's number.
+const CARD_SLOT_OFFSET: Coord = Coord::new(0, 1);
+/// Unselected cards sit down and right of selected ones, which cast a shadow there.
+const UNSELECTED_CARD_OFFSET: Coord = Coord::new(1, 1);
 
 const MAX_NUM_CARDS: usize = 8;
 
+/// The map cell under a point on the screen.
+pub fn screen_to_game_coord(coord: Coord) -> Option<Coord> {
+    let game_coord = coord - GAME_OFFSET;
+    if game_coord.x >= 0
+        && game_coord.y >= 0
+        && game_coord.x < GAME_SIZE.x
+        && game_coord.y < GAME_SIZE.y
+    {
+        Some(game_coord)
+    } else {
+        None
+    }
+}
+
+/// The hand slot of the card under a point on the screen.
+pub fn screen_to_card_slot(coord: Coord) -> Option<usize> {
+    let delta = coord - CARDS_OFFSET - CARD_SLOT_OFFSET - UNSELECTED_CARD_OFFSET;
+    let stride = CARD_SIZE.x + CARD_PADDING_X;
+    if delta.x < 0 || delta.y < 0 || delta.y >= CARD_SIZE.y {
+        return None;
+    }
+    let slot = (delta.x / stride) as usize;
+    if delta.x % stride < CARD_SIZE.x && slot < MAX_NUM_CARDS {
+        Some(slot)
+    } else {
+        None
+    }
+}
+
 struct StatusView;
 
 pub struct UiData<'a> {
@@ -39,6 +72,8 @@ pub struct UiData<'a> {
     pub message: Option<&'a str>,
     pub card_table: &'a CardTable,
     pub card_selection: Option<&'a CardInSlot>,
+    /// Slots marked for discarding while choosing a mulligan.
+    pub mulligan: Option<&'a [usize]>,
     pub view_cursor: Option<&'a Coord>,
 }
 
@@ -57,20 +92,31 @@ impl<'a> View<&'a UiData<'a>> for StatusView {
         let spent_colour = rgb24(100, 70, 180);
         let waste_colour = rgb24(100, 120, 20);
         let burnt_colour = rgb24(150, 100, 40);
+        let objective_colour = rgb24(255, 210, 0);
+        let relic_colour = rgb24(220, 170, 50);
+        let gold_colour = rgb24(255, 215, 0);
+        let item_colour = rgb24(160, 80, 255);
         let draw_countdown = ui_data.game.draw_countdown();
         let mut offset = Coord::new(0, 0);
         let stat_offset = Coord::new(7, 0);
-        StringViewSingleLine::default().view("Level:", context.add_offset(offset), grid);
+        let (level_label, level_text) = match ui_data.game.mode() {
+            GameMode::Standard => (
+                "Level:",
+                format!("{}/{}", ui_data.game.dungeon_level(), NUM_LEVELS),
+            ),
+            GameMode::Endless => ("Depth:", format!("{}", ui_data.game.dungeon_level())),
+        };
+        StringViewSingleLine::default().view(
+            level_label,
+            context.add_offset(offset),
+            grid,
+        );
         StringViewSingleLine::new(
             Style::new()
                 .with_bold(true)
                 .with_foreground(rgb24(50, 50, 200)),
         )
-        .view(
-            &format!("{}/{}", ui_data.game.dungeon_level(), 6),
-            context.add_offset(offset + stat_offset),
-            grid,
-        );
+        .view(&level_text, context.add_offset(offset + stat_offset), grid);
         offset += Coord::new(0, 2);
         StringViewSingleLine::default().view("Life:", context.add_offset(offset), grid);
         StringViewSingleLine::new(
@@ -131,6 +177,350 @@ impl<'a> View<&'a UiData<'a>> for StatusView {
             context.add_offset(offset + stat_offset),
             grid,
         );
+        offset += Coord::new(0, 2);
+        StringViewSingleLine::default().view("Gold:", context.add_offset(offset), grid);
+        StringViewSingleLine::new(
+            Style::new().with_bold(true).with_foreground(gold_colour),
+        )
+        .view(
+            &format!("{}", ui_data.game.gold()),
+            context.add_offset(offset + stat_offset),
+            grid,
+        );
+        if let Some(boss) = ui_data.game.boss() {
+            let boss_hit_points = boss.hit_points().unwrap();
+            offset += Coord::new(0, 2);
+            StringViewSingleLine::default().view(
+                "Boss:",
+                context.add_offset(offset),
+                grid,
+            );
+            StringViewSingleLine::new(
+                Style::new()
+                    .with_bold(true)
+                    .with_foreground(rgb24(255, 120, 0)),
+            )
+            .view(
+                &format!("{}/{}", boss_hit_points.current, boss_hit_points.max),
+                context.add_offset(offset + stat_offset),
+                grid,
+            );
+        }
+        if let Some(objective) = ui_data.game.objective() {
+            offset += Coord::new(0, 2);
+            StringViewSingleLine::default().view(
+                "Goal:",
+                context.add_offset(offset),
+                grid,
+            );
+            offset += Coord::new(0, 1);
+            let description = match objective {
+                Objective::ClearEnemies => "Kill all".to_string(),
+                Objective::ReachStairsWithin { turns } => format!(
+                    "Exit in {}",
+                    turns.saturating_sub(ui_data.game.turns_taken())
+                ),
+                Objective::CollectKey => "Find key".to_string(),
+                Objective::NoHealing => "No healing".to_string(),
+            };
+            StringViewSingleLine::new(
+                Style::new()
+                    .with_bold(true)
+                    .with_foreground(objective_colour),
+            )
+            .view(&description, context.add_offset(offset), grid);
+            offset += Coord::new(0, 1);
+            match ui_data.game.objective_status() {
+                Some(ObjectiveStatus::Complete) => {
+                    StringViewSingleLine::new(
+                        Style::new().with_foreground(rgb24(0, 200, 0)),
+                    )
+                    .view(
+                        "Complete",
+                        context.add_offset(offset),
+                        grid,
+                    );
+                }
+                Some(ObjectiveStatus::Failed) => {
+                    StringViewSingleLine::new(
+                        Style::new().with_foreground(rgb24(200, 0, 0)),
+                    )
+                    .view("Failed", context.add_offset(offset), grid);
+                }
+                Some(ObjectiveStatus::InProgress) | None => (),
+            }
+        }
+        let relics = ui_data.game.relics();
+        if !relics.is_empty() {
+            offset += Coord::new(0, 2);
+            StringViewSingleLine::default().view(
+                "Relics:",
+                context.add_offset(offset),
+                grid,
+            );
+            for &relic in relics {
+                offset += Coord::new(0, 1);
+                StringViewSingleLine::new(
+                    Style::new().with_bold(true).with_foreground(relic_colour),
+                )
+                .view(
+                    relic_text(relic),
+                    context.add_offset(offset),
+                    grid,
+                );
+            }
+        }
+        let items = ui_data.game.items();
+        offset += Coord::new(0, 2);
+        StringViewSingleLine::default().view(
+            &format!("Items {}/{}:", items.len(), MAX_ITEMS),
+            context.add_offset(offset),
+            grid,
+        );
+        for &item in items {
+            offset += Coord::new(0, 1);
+            StringViewSingleLine::new(
+                Style::new().with_bold(true).with_foreground(item_colour),
+            )
+            .view(item_short_text(item), context.add_offset(offset), grid);
+        }
+    }
+}
+
+fn direction_name(direction: CardinalDirection) -> &'static str {
+    match direction {
+        CardinalDirection::North => "north",
+        CardinalDirection::East => "east",
+        CardinalDirection::South => "south",
+        CardinalDirection::West => "west",
+    }
+}
+
+fn item_short_text(item: Item) -> &'static str {
+    match item {
+        Item::Bomb => "Bomb",
+        Item::MappingScroll => "Scroll",
+        Item::Torch => "Torch",
+        Item::TeleportCharm => "Charm",
+    }
+}
+
+fn keyword_text(keyword: Keyword) -> &'static str {
+    match keyword {
+        Keyword::Retain => "Retain",
+        Keyword::Exhaust => "Exhaust",
+        Keyword::Innate => "Innate",
+        Keyword::Ethereal => "Ethereal",
+    }
+}
+
+fn elite_trait_text(elite_trait: EliteTrait) -> &'static str {
+    match elite_trait {
+        EliteTrait::Armoured => "Armoured",
+        EliteTrait::Hasted => "Hasted: acts twice",
+        EliteTrait::Regenerating => "Regenerating",
+        EliteTrait::Explosive => "Explosive: bursts on death",
+        EliteTrait::LightAbsorbing => "Light-absorbing",
+    }
+}
+
+/// Describes an action taken by an npc standing at `coord`.
+fn intent(
+    coord: Coord,
+    (direction, typ): (CardinalDirection, CommitmentType),
+    player_coord: Coord,
+) -> String {
+    match typ {
+        CommitmentType::Move => {
+            if coord + direction.coord() == player_coord {
+                "Will attack you".to_string()
+            } else {
+                format!("Will move {}", direction_name(direction))
+            }
+        }
+        CommitmentType::Cast => format!("Will cast spark {}", direction_name(direction)),
+        CommitmentType::Heal(0) => "Will heal nearby enemies".to_string(),
+        CommitmentType::Slam => "Will slam everything next to it".to_string(),
+        CommitmentType::Summon => {
+            format!("Will summon a bruiser {}", direction_name(direction))
+        }
+        CommitmentType::Heal(_) => "Preparing to heal".to_string(),
+    }
+}
+
+/// Detailed information about the npc or interactive at a location.
+fn inspect(game: &Gws, coord: Coord, card_table: &CardTable) -> Vec<String> {
+    let to_render = game.to_render();
+    let mut lines = Vec::new();
+    let cell = match to_render.world.grid().get(coord) {
+        Some(cell) => cell,
+        None => return lines,
+    };
+    if !to_render.visible_area.is_visible(coord)
+        || to_render.visible_area.light_colour(coord) == grey24(0)
+    {
+        return lines;
+    }
+    for entity in cell.entity_iter(to_render.world.entities()) {
+        if entity.is_npc() {
+            if let Some(hit_points) = entity.hit_points() {
+                lines.push(format!("Life: {}/{}", hit_points.current, hit_points.max));
+            }
+            if let Some(heal_countdown) = entity.heal_countdown() {
+                lines.push(format!("Heals in {} turns", heal_countdown));
+            }
+            if let Some(boss_phase) = entity.boss_phase() {
+                lines.push(
+                    match boss_phase {
+                        BossPhase::Pursuit => "Phase 1: pursuit",
+                        BossPhase::Shockwave => "Phase 2: shockwave",
+                        BossPhase::Frenzy => "Phase 3: frenzy",
+                    }
+                    .to_string(),
+                );
+            }
+            if entity.is_elite() {
+                lines.push("Elite: kill for a bonus upgrade".to_string());
+                for &elite_trait in entity.elite_traits() {
+                    lines.push(format!("- {}", elite_trait_text(elite_trait)));
+                }
+                if entity.armour() > 0 {
+                    lines.push(format!("Armour: {}", entity.armour()));
+                }
+            }
+            match entity.frozen_turns() {
+                Some(0) => lines.push("Thaws next turn".to_string()),
+                Some(frozen_turns) => {
+                    lines.push(format!("Frozen for {} turns", frozen_turns))
+                }
+                None => {
+                    if entity.is_wading() {
+                        lines.push("Wading through water".to_string());
+                    }
+                    match game.committed_action(entity.id()) {
+                        Some(action) => {
+                            let player_coord = to_render.player.coord();
+                            lines.push(intent(entity.coord(), action, player_coord));
+                            if let Some(followup) = game.followup_action(entity.id()) {
+                                let (direction, typ) = action;
+                                let moved_coord = entity.coord() + direction.coord();
+                                let coord = match typ {
+                                    CommitmentType::Move
+                                        if moved_coord != player_coord =>
+                                    {
+                                        moved_coord
+                                    }
+                                    _ => entity.coord(),
+                                };
+                                lines.push(format!(
+                                    "Then: {}",
+                                    intent(coord, followup, player_coord)
+                                ));
+                            }
+                        }
+                        None => {
+                            if !entity.is_wading() {
+                                lines.push("Idle".to_string())
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(upgrade) = entity.upgrade() {
+            match entity.foreground_tile() {
+                Some(ForegroundTile::Altar) => {
+                    lines.push("Choose an upgrade and a curse:".to_string());
+                    for (&character_upgrade, &card) in upgrade
+                        .character_upgrades
+                        .iter()
+                        .zip(upgrade.negative_cards.iter())
+                    {
+                        lines.push(format!(
+                            "{} + {}",
+                            upgrade_text(character_upgrade),
+                            card_table.get(card).title
+                        ));
+                    }
+                }
+                Some(ForegroundTile::Merchant) => {
+                    lines.push("For sale:".to_string());
+                    for &card in upgrade.positive_cards.iter() {
+                        lines.push(card_table.get(card).title.to_string());
+                    }
+                    for &character_upgrade in upgrade.character_upgrades.iter() {
+                        lines.push(upgrade_text(character_upgrade).to_string());
+                    }
+                }
+                Some(ForegroundTile::Fountain) => {
+                    lines.push("Choose a card:".to_string());
+                    for (&card, &count) in
+                        upgrade.positive_cards.iter().zip(upgrade.counts.iter())
+                    {
+                        lines.push(format!("{}x {}", count, card_table.get(card).title));
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+    if !lines.is_empty() {
+        if let Some(title) = describe(game, coord) {
+            lines.insert(0, title);
+        }
+    }
+    lines
+}
+
+struct InspectView;
+
+impl<'a> View<(&'a [String], Coord)> for InspectView {
+    fn view<G: ViewGrid, R: ViewTransformRgb24>(
+        &mut self,
+        (lines, view_cursor): (&'a [String], Coord),
+        context: ViewContext<R>,
+        grid: &mut G,
+    ) {
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count() as i32)
+            .max()
+            .unwrap_or(0)
+            + 2;
+        let height = lines.len() as i32 + 2;
+        // keep out of the way of the thing being inspected
+        let offset = if view_cursor.x < GAME_SIZE.x / 2 {
+            Coord::new(GAME_SIZE.x - width, 0)
+        } else {
+            Coord::new(0, 0)
+        };
+        let background = rgb24(20, 20, 40);
+        for y in 0..height {
+            for x in 0..width {
+                grid.set_cell_relative(
+                    offset + Coord::new(x, y),
+                    2,
+                    ViewCell::new()
+                        .with_character(' ')
+                        .with_background(background),
+                    context,
+                );
+            }
+        }
+        for (i, line) in lines.iter().enumerate() {
+            let style = if i == 0 {
+                Style::new().with_bold(true)
+            } else {
+                Style::new()
+            };
+            StringViewSingleLine::new(style.with_background(background)).view(
+                line,
+                context
+                    .add_offset(offset + Coord::new(1, i as i32 + 1))
+                    .add_depth(3),
+                grid,
+            );
+        }
     }
 }
 
@@ -172,15 +562,20 @@ impl<'a, V: View<&'a Gws>> View<&'a UiData<'a>> for UiView<V> {
                 ViewCell::new().with_background(rgb24(0, 255, 255)),
                 context,
             );
+            let lines = inspect(ui_data.game, *coord, ui_data.card_table);
+            if !lines.is_empty() {
+                InspectView.view((&lines, *coord), context.add_offset(GAME_OFFSET), grid);
+            }
         }
         StatusView.view(ui_data, context.add_offset(STATUS_OFFSET), grid);
+        let selected_slot = ui_data.card_selection.map(|cs| [cs.slot]);
+        let selected_slots = match (ui_data.mulligan, selected_slot.as_ref()) {
+            (Some(slots), _) => slots,
+            (None, Some(slot)) => slot,
+            (None, None) => &[],
+        };
         CardAreaView.view(
-            (
-                ui_data.game.hand(),
-                ui_data.card_table,
-                ui_data.card_selection.map(|cs| cs.slot),
-                *ui_data.game.draw_countdown(),
-            ),
+            (ui_data.game, ui_data.card_table, selected_slots),
             context.add_offset(CARDS_OFFSET),
             grid,
         );
@@ -197,6 +592,8 @@ impl<'a, V: View<&'a Gws>> View<&'a UiData<'a>> for UiView<V> {
 struct CardView;
 struct CardAreaView;
 
+pub const UPGRADED_CARD_COLOUR: Rgb24 = rgb24(255, 200, 60);
+
 pub struct CardInfo {
     pub card: Card,
     pub title: String,
@@ -205,7 +602,15 @@ pub struct CardInfo {
 }
 
 impl CardInfo {
-    fn new(card: Card, title: String, description: String, background: Rgb24) -> Self {
+    fn new(
+        card: Card,
+        title: String,
+        mut description: String,
+        background: Rgb24,
+    ) -> Self {
+        for &keyword in card.keywords() {
+            description.push_str(&format!(" {}.", keyword_text(keyword)));
+        }
         Self {
             card,
             title,
@@ -247,6 +652,21 @@ pub struct CardTable {
     spend: CardInfo,
     deposit: CardInfo,
     caltrop: CardInfo,
+    smash: CardInfo,
+    bump_plus: CardInfo,
+    blink_plus: CardInfo,
+    heal_plus: CardInfo,
+    spark_plus: CardInfo,
+    block_plus: CardInfo,
+    freeze_plus: CardInfo,
+    spike_plus: CardInfo,
+    surround_plus: CardInfo,
+    shred_plus: CardInfo,
+    deposit_plus: CardInfo,
+    caltrop_plus: CardInfo,
+    poise: CardInfo,
+    flicker: CardInfo,
+    rampart: CardInfo,
 }
 
 impl CardTable {
@@ -285,13 +705,13 @@ impl CardTable {
             parasite: CardInfo::new(
                 Card::Parasite,
                 "Parasite".to_string(),
-                "Take 2 damage. Burn this card.".to_string(),
+                "Take 2 damage.".to_string(),
                 rgb24(20, 20, 20),
             ),
             drain: CardInfo::new(
                 Card::Drain,
                 "Drain".to_string(),
-                "Burn this card.".to_string(),
+                "Has no effect.".to_string(),
                 rgb24(20, 20, 20),
             ),
             block: CardInfo::new(
@@ -390,6 +810,96 @@ impl CardTable {
                 "Move, leaving behind a temporary spike.".to_string(),
                 rgb24(0, 20, 0),
             ),
+            smash: CardInfo::new(
+                Card::Smash,
+                "Smash".to_string(),
+                "Destroy an adjacent cracked wall or door.".to_string(),
+                rgb24(20, 0, 0),
+            ),
+            bump_plus: CardInfo::new(
+                Card::BumpPlus,
+                "Bump+".to_string(),
+                "Attack adjacent square for 3 damage".to_string(),
+                rgb24(20, 0, 0),
+            ),
+            blink_plus: CardInfo::new(
+                Card::BlinkPlus,
+                "Blink+".to_string(),
+                "Teleport to visible square up to 12 away".to_string(),
+                rgb24(0, 20, 0),
+            ),
+            heal_plus: CardInfo::new(
+                Card::HealPlus,
+                "Heal+".to_string(),
+                "Recover 1 hit point".to_string(),
+                rgb24(0, 20, 0),
+            ),
+            spark_plus: CardInfo::new(
+                Card::SparkPlus,
+                "Spark+".to_string(),
+                "Shoot a spark dealing 1 damage with 10 range.".to_string(),
+                rgb24(20, 0, 0),
+            ),
+            block_plus: CardInfo::new(
+                Card::BlockPlus,
+                "Block+".to_string(),
+                "Summon a block which lasts 8 turns.".to_string(),
+                rgb24(0, 20, 0),
+            ),
+            freeze_plus: CardInfo::new(
+                Card::FreezePlus,
+                "Freeze+".to_string(),
+                "Prevent an enemy from moving for 16 turns.".to_string(),
+                rgb24(0, 20, 0),
+            ),
+            spike_plus: CardInfo::new(
+                Card::SpikePlus,
+                "Spike+".to_string(),
+                "Summon a spike trap which lasts 8 turns.".to_string(),
+                rgb24(20, 0, 0),
+            ),
+            surround_plus: CardInfo::new(
+                Card::SurroundPlus,
+                "Surround+".to_string(),
+                "Surround a square with 8 walls lasting 8 turns each.".to_string(),
+                rgb24(0, 20, 0),
+            ),
+            shred_plus: CardInfo::new(
+                Card::ShredPlus,
+                "Shred+".to_string(),
+                "Summon 9 spike traps lasting 8 turns each.".to_string(),
+                rgb24(20, 0, 0),
+            ),
+            deposit_plus: CardInfo::new(
+                Card::DepositPlus,
+                "Deposit+".to_string(),
+                "Move, leaving behind a temporary wall.".to_string(),
+                rgb24(0, 20, 0),
+            ),
+            caltrop_plus: CardInfo::new(
+                Card::CaltropPlus,
+                "Caltrop+".to_string(),
+                "Move, leaving behind a temporary spike.".to_string(),
+                rgb24(0, 20, 0),
+            ),
+            poise: CardInfo::new(
+                Card::Poise,
+                "Poise".to_string(),
+                "Recover 1 hit point.".to_string(),
+                rgb24(0, 20, 0),
+            ),
+            flicker: CardInfo::new(
+                Card::Flicker,
+                "Flicker".to_string(),
+                "Teleport up to 8 away.".to_string(),
+                rgb24(0, 20, 0),
+            ),
+            rampart: CardInfo::new(
+                Card::Rampart,
+                "Rampart".to_string(),
+                "Wall yourself in for 8 turns.".to_string(),
+                rgb24(0, 20, 0),
+            ),
         }
     }
     pub fn get(&self, card: Card) -> &CardInfo {
@@ -417,30 +927,35 @@ impl CardTable {
             Card::Burn => &self.burn,
             Card::Deposit => &self.deposit,
             Card::Caltrop => &self.caltrop,
+            Card::Smash => &self.smash,
+            Card::BumpPlus => &self.bump_plus,
+            Card::BlinkPlus => &self.blink_plus,
+            Card::HealPlus => &self.heal_plus,
+            Card::SparkPlus => &self.spark_plus,
+            Card::BlockPlus => &self.block_plus,
+            Card::FreezePlus => &self.freeze_plus,
+            Card::SpikePlus => &self.spike_plus,
+            Card::SurroundPlus => &self.surround_plus,
+            Card::ShredPlus => &self.shred_plus,
+            Card::DepositPlus => &self.deposit_plus,
+            Card::CaltropPlus => &self.caltrop_plus,
+            Card::Poise => &self.poise,
+            Card::Flicker => &self.flicker,
+            Card::Rampart => &self.rampart,
         }
     }
 }
 
-impl<'a>
-    View<(
-        &'a [Option<Card>],
-        &'a CardTable,
-        Option<usize>,
-        DrawCountdown,
-    )> for CardAreaView
-{
+impl<'a> View<(&'a Gws, &'a CardTable, &'a [usize])> for CardAreaView {
     fn view<G: ViewGrid, R: ViewTransformRgb24>(
         &mut self,
-        (cards, card_table, selected_slot, draw_countdown): (
-            &'a [Option<Card>],
-            &'a CardTable,
-            Option<usize>,
-            DrawCountdown,
-        ),
+        (game, card_table, selected_slots): (&'a Gws, &'a CardTable, &'a [usize]),
 
         context: ViewContext<R>,
         grid: &mut G,
     ) {
+        let cards = game.hand();
+        let draw_countdown = *game.draw_countdown();
         for i in 0..MAX_NUM_CARDS {
             let offset_x = i as i32 * (CARD_SIZE.x + CARD_PADDING_X);
             StringViewSingleLine::default().view(
@@ -448,13 +963,17 @@ impl<'a>
                 context.add_offset(Coord::new(offset_x + 4, 0)),
                 grid,
             );
-            let offset = Coord::new(offset_x, 1);
+            let offset = CARD_SLOT_OFFSET + Coord::new(offset_x, 0);
             if let Some(maybe_card) = cards.get(i) {
                 if let Some(card) = maybe_card.as_ref() {
-                    let selected =
-                        selected_slot.map(|s| s == i as usize).unwrap_or(false);
+                    let selected = selected_slots.contains(&i);
                     CardView.view(
-                        (card_table.get(*card), selected, draw_countdown),
+                        (
+                            card_table.get(*card),
+                            game.card_cost(*card),
+                            selected,
+                            draw_countdown,
+                        ),
                         context.add_offset(offset),
                         grid,
                     );
@@ -518,27 +1037,37 @@ fn empty_card_view<G: ViewGrid, R: ViewTransformRgb24>(
         .with_character('░')
         .with_foreground(grey24(20));
     for coord in XThenYIter::new(CARD_SIZE.to_size().unwrap()) {
-        grid.set_cell_relative(coord + Coord::new(1, 1), 0, view_cell, context);
+        grid.set_cell_relative(coord + UNSELECTED_CARD_OFFSET, 0, view_cell, context);
     }
 }
 
-impl<'a> View<(&'a CardInfo, bool, DrawCountdown)> for CardView {
+impl<'a> View<(&'a CardInfo, u32, bool, DrawCountdown)> for CardView {
     fn view<G: ViewGrid, R: ViewTransformRgb24>(
         &mut self,
-        (card_info, selected, draw_countdown): (&'a CardInfo, bool, DrawCountdown),
+        (card_info, energy_cost, selected, draw_countdown): (
+            &'a CardInfo,
+            u32,
+            bool,
+            DrawCountdown,
+        ),
         context: ViewContext<R>,
         grid: &mut G,
     ) {
         let selected_offset = if selected {
             Coord::new(0, 0)
         } else {
-            Coord::new(1, 1)
+            UNSELECTED_CARD_OFFSET
+        };
+        let title_colour = if card_info.card.is_upgraded() {
+            UPGRADED_CARD_COLOUR
+        } else {
+            colours::WHITE
         };
         StringViewSingleLine::new(
             Style::new()
                 .with_bold(true)
                 .with_underline(true)
-                .with_foreground(colours::WHITE),
+                .with_foreground(title_colour),
         )
         .view(
             &card_info.title,
@@ -555,7 +1084,6 @@ impl<'a> View<(&'a CardInfo, bool, DrawCountdown)> for CardView {
                 .add_depth(1),
             grid,
         );
-        let energy_cost = card_info.card.cost();
         let energy_colour = if draw_countdown.current < energy_cost {
             rgb24(255, 0, 0)
         } else {
@@ -640,17 +1168,12 @@ impl<'a> View<&'a UiData<'a>> for DeathView {
         DeathGameView.view(ui_data.game, context.add_offset(GAME_OFFSET), grid);
         StatusView.view(ui_data, context.add_offset(STATUS_OFFSET), grid);
         StringViewSingleLine::default().view(
-            "You died. Press any key...",
+            ui_data.message.unwrap_or("You died. Press any key..."),
             context.add_offset(MESSAGE_OFFSET),
             grid,
         );
         CardAreaView.view(
-            (
-                ui_data.game.hand(),
-                ui_data.card_table,
-                None,
-                *ui_data.game.draw_countdown(),
-            ),
+            (ui_data.game, ui_data.card_table, &[]),
             context.add_offset(CARDS_OFFSET),
             grid,
         );
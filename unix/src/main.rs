@@ -39,6 +39,7 @@ fn main() {
     let args = CommonArgs::arg()
         .with_help_default()
         .parse_env_default_or_exit();
+    let wfc_sample = args.wfc_sample();
     let mut context = Context::with_colour_config(GwsColourConfig).unwrap();
     let storage =
         FileStorage::next_to_exe(args.save_dir(), true).expect("Failed to find user dir");
@@ -47,6 +48,7 @@ fn main() {
         storage,
         args.first_rng_seed(),
         args.debug_terrain_string(),
+        wfc_sample,
     );
     let mut app_view = AppView::new();
     let mut frame_instant = Instant::now();
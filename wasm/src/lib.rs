@@ -31,7 +31,7 @@ impl WebApp {
     pub fn new(js_grid: JsGrid, js_byte_storage: JsByteStorage) -> Self {
         let storage = WasmStorage::new(js_byte_storage);
         let (app, init_status) =
-            App::new(frontend::Wasm, storage, FirstRngSeed::Random, None);
+            App::new(frontend::Wasm, storage, FirstRngSeed::Random, None, None);
         let app_view = AppView::new();
         match init_status {
             InitStatus::NoSaveFound => console_log!("No save game found"),